@@ -0,0 +1,73 @@
+pub mod audio_data {
+    use bevy::prelude::*;
+
+    /// Default sounds under the asset root, played when they are there.
+    /// The game ships without any; drop MP3 files in with these names to
+    /// hear them, or point `SoundPaths` at an app's own.
+    pub const FRUIT_SOUND: &str = "sounds/fruit.mp3";
+    pub const DEATH_SOUND: &str = "sounds/death.mp3";
+
+    /// Resource: which sound to play for each event, as a path under the
+    /// asset root. `None` keeps that event quiet.
+    #[derive(Clone, Debug)]
+    pub struct SoundPaths {
+        pub fruit: Option<String>,
+        pub death: Option<String>,
+    }
+
+    /// Resource: the sounds found at startup.
+    #[derive(Default)]
+    pub struct Sounds {
+        pub fruit: Option<Handle<AudioSource>>,
+        pub death: Option<Handle<AudioSource>>,
+    }
+}
+
+pub mod audio_functions {
+    use bevy::prelude::*;
+    use super::audio_data::*;
+    use crate::events::events_data::*;
+    use crate::theme::theme_functions::*;
+
+    impl Default for SoundPaths {
+        fn default() -> Self {
+            SoundPaths {
+                fruit: Some(FRUIT_SOUND.to_string()),
+                death: Some(DEATH_SOUND.to_string()),
+            }
+        }
+    }
+
+    /// Loads whichever sounds are present, so a missing one is silent
+    /// rather than an asset error.
+    pub fn load_sounds(asset_server: Res<AssetServer>, paths: Res<SoundPaths>, mut sounds: ResMut<Sounds>) {
+        let root = asset_root();
+        let load = |path: &Option<String>| match path {
+            Some(path) if root.join(path).exists() => Some(asset_server.load(path.as_str())),
+            _ => None,
+        };
+        sounds.fruit = load(&paths.fruit);
+        sounds.death = load(&paths.death);
+    }
+
+    /// Plays a sound when fruit is eaten and when the snake dies.
+    pub fn play_sounds(
+        mut fruit_reader: Local<EventReader<EventFruitEaten>>,
+        fruit_events: Res<Events<EventFruitEaten>>,
+        mut died_reader: Local<EventReader<EventSnakeDied>>,
+        died_events: Res<Events<EventSnakeDied>>,
+        sounds: Res<Sounds>,
+        audio: Res<Audio>,
+    ) {
+        for _ in fruit_reader.iter(&fruit_events) {
+            if let Some(sound) = &sounds.fruit {
+                audio.play(sound.clone());
+            }
+        }
+        for _ in died_reader.iter(&died_events) {
+            if let Some(sound) = &sounds.death {
+                audio.play(sound.clone());
+            }
+        }
+    }
+}
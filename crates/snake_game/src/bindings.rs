@@ -0,0 +1,463 @@
+pub mod bindings_data {
+    use bevy::prelude::*;
+    use serde::{Deserialize, Deserializer, Serialize};
+    use std::collections::HashMap;
+    use crate::steering::steering_data::*;
+
+    /// Something the player can do with a key during a game. Menus keep
+    /// their fixed keys (arrows, Enter, escape) so they can always be used
+    /// to fix a bad binding.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub enum Action {
+        Up,
+        Down,
+        Left,
+        Right,
+        Pause,
+        Restart,
+        /// Held to move faster.
+        Boost,
+        /// The single switch in `SteeringMode::OneSwitch`.
+        Switch,
+    }
+
+    /// Keyboard layout the default steering keys are picked for, so the
+    /// letter keys sit in the same place on either.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum KeyboardLayout {
+        /// Arrows and WASD.
+        Qwerty,
+        /// Arrows and ZQSD.
+        Azerty,
+    }
+
+    /// Keys for each action, by name, as stored in the config file. An
+    /// action may have several keys, so the arrows and the letter keys for
+    /// `layout` both work out of the box.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(from = "ControlsFile")]
+    pub struct ControlsConfig {
+        pub steering: SteeringMode,
+        pub layout: KeyboardLayout,
+        pub up: Vec<String>,
+        pub down: Vec<String>,
+        pub left: Vec<String>,
+        pub right: Vec<String>,
+        pub pause: Vec<String>,
+        pub restart: Vec<String>,
+        pub boost: Vec<String>,
+        pub switch: Vec<String>,
+    }
+
+    /// `ControlsConfig` as written in the file. Actions left out get the
+    /// defaults for the file's `layout`.
+    #[derive(Default, Deserialize)]
+    #[serde(default)]
+    pub struct ControlsFile {
+        pub steering: SteeringMode,
+        pub layout: KeyboardLayout,
+        #[serde(deserialize_with = "listed_keys")]
+        pub up: Option<Vec<String>>,
+        #[serde(deserialize_with = "listed_keys")]
+        pub down: Option<Vec<String>>,
+        #[serde(deserialize_with = "listed_keys")]
+        pub left: Option<Vec<String>>,
+        #[serde(deserialize_with = "listed_keys")]
+        pub right: Option<Vec<String>>,
+        #[serde(deserialize_with = "listed_keys")]
+        pub pause: Option<Vec<String>>,
+        #[serde(deserialize_with = "listed_keys")]
+        pub restart: Option<Vec<String>>,
+        #[serde(deserialize_with = "listed_keys")]
+        pub boost: Option<Vec<String>>,
+        #[serde(deserialize_with = "listed_keys")]
+        pub switch: Option<Vec<String>>,
+    }
+
+    /// Reads a plain list of keys, so the file does not need `Some(..)`.
+    fn listed_keys<'de, D: Deserializer<'de>>(deserializer: D) -> Result<Option<Vec<String>>, D::Error> {
+        Vec::deserialize(deserializer).map(Some)
+    }
+
+    /// Resource: the keys bound to each action, built from `ControlsConfig`.
+    #[derive(Clone, Debug, Default)]
+    pub struct KeyBindings {
+        pub steering: SteeringMode,
+        pub layout: KeyboardLayout,
+        pub keys: HashMap<Action, Vec<KeyCode>>,
+    }
+
+    /// Resource: the controls screen, opened from the settings screen.
+    /// `listening` is set while waiting for the key to bind to the
+    /// highlighted action.
+    #[derive(Default)]
+    pub struct ControlsMenu {
+        pub open: bool,
+        pub cursor: usize,
+        pub listening: bool,
+        /// Feedback on the last change, such as a rejected key.
+        pub message: Option<String>,
+    }
+
+    /// Names used for keys in the config file.
+    pub const KEY_NAMES: [(KeyCode, &str); 74] = [
+        (KeyCode::Key1, "1"), (KeyCode::Key2, "2"), (KeyCode::Key3, "3"), (KeyCode::Key4, "4"),
+        (KeyCode::Key5, "5"), (KeyCode::Key6, "6"), (KeyCode::Key7, "7"), (KeyCode::Key8, "8"),
+        (KeyCode::Key9, "9"), (KeyCode::Key0, "0"),
+        (KeyCode::A, "A"), (KeyCode::B, "B"), (KeyCode::C, "C"), (KeyCode::D, "D"), (KeyCode::E, "E"),
+        (KeyCode::F, "F"), (KeyCode::G, "G"), (KeyCode::H, "H"), (KeyCode::I, "I"), (KeyCode::J, "J"),
+        (KeyCode::K, "K"), (KeyCode::L, "L"), (KeyCode::M, "M"), (KeyCode::N, "N"), (KeyCode::O, "O"),
+        (KeyCode::P, "P"), (KeyCode::Q, "Q"), (KeyCode::R, "R"), (KeyCode::S, "S"), (KeyCode::T, "T"),
+        (KeyCode::U, "U"), (KeyCode::V, "V"), (KeyCode::W, "W"), (KeyCode::X, "X"), (KeyCode::Y, "Y"),
+        (KeyCode::Z, "Z"),
+        (KeyCode::Escape, "Escape"), (KeyCode::F1, "F1"), (KeyCode::F2, "F2"), (KeyCode::F3, "F3"),
+        (KeyCode::F4, "F4"), (KeyCode::F5, "F5"), (KeyCode::F6, "F6"), (KeyCode::F7, "F7"),
+        (KeyCode::F8, "F8"), (KeyCode::F9, "F9"), (KeyCode::F10, "F10"), (KeyCode::F11, "F11"),
+        (KeyCode::F12, "F12"),
+        (KeyCode::Up, "Up"), (KeyCode::Down, "Down"), (KeyCode::Left, "Left"), (KeyCode::Right, "Right"),
+        (KeyCode::Back, "Backspace"), (KeyCode::Return, "Enter"), (KeyCode::Space, "Space"),
+        (KeyCode::Tab, "Tab"), (KeyCode::LShift, "LShift"), (KeyCode::RShift, "RShift"),
+        (KeyCode::LControl, "LControl"), (KeyCode::RControl, "RControl"),
+        (KeyCode::Comma, "Comma"), (KeyCode::Period, "Period"), (KeyCode::Semicolon, "Semicolon"),
+        (KeyCode::Slash, "Slash"), (KeyCode::Minus, "Minus"), (KeyCode::Equals, "Equals"),
+        (KeyCode::Numpad2, "Numpad2"), (KeyCode::Numpad4, "Numpad4"), (KeyCode::Numpad6, "Numpad6"),
+        (KeyCode::Numpad8, "Numpad8"),
+        (KeyCode::Home, "Home"), (KeyCode::End, "End"), (KeyCode::Delete, "Delete"),
+    ];
+}
+
+pub mod bindings_functions {
+    use bevy::prelude::*;
+    use std::path::Path;
+    use super::bindings_data::*;
+    use crate::config::config_data::*;
+    use crate::snake::snake_data::*;
+    use crate::steering::steering_data::*;
+
+    impl Action {
+        pub const ALL: [Action; 8] = [
+            Action::Up,
+            Action::Down,
+            Action::Left,
+            Action::Right,
+            Action::Pause,
+            Action::Restart,
+            Action::Boost,
+            Action::Switch,
+        ];
+
+        pub fn label(self) -> &'static str {
+            match self {
+                Action::Up => "Up",
+                Action::Down => "Down",
+                Action::Left => "Left",
+                Action::Right => "Right",
+                Action::Pause => "Pause",
+                Action::Restart => "Start / retry",
+                Action::Boost => "Boost",
+                Action::Switch => "Switch",
+            }
+        }
+
+        /// The direction a steering action turns the snake.
+        pub fn direction(self) -> Option<SnakeDirection> {
+            match self {
+                Action::Up => Some(SnakeDirection::UP),
+                Action::Down => Some(SnakeDirection::DOWN),
+                Action::Left => Some(SnakeDirection::LEFT),
+                Action::Right => Some(SnakeDirection::RIGHT),
+                _ => None,
+            }
+        }
+    }
+
+    impl Default for KeyboardLayout {
+        fn default() -> Self {
+            KeyboardLayout::Qwerty
+        }
+    }
+
+    impl Default for ControlsConfig {
+        fn default() -> Self {
+            ControlsConfig::for_layout(KeyboardLayout::default())
+        }
+    }
+
+    impl From<ControlsFile> for ControlsConfig {
+        fn from(file: ControlsFile) -> Self {
+            let defaults = ControlsConfig::for_layout(file.layout);
+            ControlsConfig {
+                steering: file.steering,
+                layout: file.layout,
+                up: file.up.unwrap_or(defaults.up),
+                down: file.down.unwrap_or(defaults.down),
+                left: file.left.unwrap_or(defaults.left),
+                right: file.right.unwrap_or(defaults.right),
+                pause: file.pause.unwrap_or(defaults.pause),
+                restart: file.restart.unwrap_or(defaults.restart),
+                boost: file.boost.unwrap_or(defaults.boost),
+                switch: file.switch.unwrap_or(defaults.switch),
+            }
+        }
+    }
+
+    impl ControlsConfig {
+        /// The default keys for `layout`.
+        pub fn for_layout(layout: KeyboardLayout) -> ControlsConfig {
+            let names = |keys: &[&str]| keys.iter().map(|key| key.to_string()).collect();
+            let (up, left) = match layout {
+                KeyboardLayout::Qwerty => ("W", "A"),
+                KeyboardLayout::Azerty => ("Z", "Q"),
+            };
+            ControlsConfig {
+                steering: SteeringMode::default(),
+                layout,
+                up: names(&["Up", up]),
+                down: names(&["Down", "S"]),
+                left: names(&["Left", left]),
+                right: names(&["Right", "D"]),
+                pause: names(&["P", "Escape"]),
+                restart: names(&["Space"]),
+                boost: names(&["LShift", "RShift"]),
+                switch: names(&["Enter"]),
+            }
+        }
+
+        pub fn keys(&self, action: Action) -> &Vec<String> {
+            match action {
+                Action::Up => &self.up,
+                Action::Down => &self.down,
+                Action::Left => &self.left,
+                Action::Right => &self.right,
+                Action::Pause => &self.pause,
+                Action::Restart => &self.restart,
+                Action::Boost => &self.boost,
+                Action::Switch => &self.switch,
+            }
+        }
+
+        fn keys_mut(&mut self, action: Action) -> &mut Vec<String> {
+            match action {
+                Action::Up => &mut self.up,
+                Action::Down => &mut self.down,
+                Action::Left => &mut self.left,
+                Action::Right => &mut self.right,
+                Action::Pause => &mut self.pause,
+                Action::Restart => &mut self.restart,
+                Action::Boost => &mut self.boost,
+                Action::Switch => &mut self.switch,
+            }
+        }
+
+        /// Lists unknown key names, actions without a key and keys bound to
+        /// more than one action.
+        pub fn problems(&self) -> Vec<String> {
+            let mut problems = Vec::new();
+            let mut seen: Vec<(KeyCode, Action)> = Vec::new();
+            for action in Action::ALL.iter() {
+                let names = self.keys(*action);
+                if names.is_empty() {
+                    problems.push(format!("`controls` has no key for {}", action.label()));
+                }
+                for name in names {
+                    let key = match key_from_name(name) {
+                        Some(key) => key,
+                        None => {
+                            problems.push(format!("`controls` has unknown key `{}`", name));
+                            continue;
+                        }
+                    };
+                    match seen.iter().find(|(other, _)| *other == key) {
+                        Some((_, other)) if other != action => problems.push(format!(
+                            "`controls` binds {} to both {} and {}",
+                            name,
+                            other.label(),
+                            action.label()
+                        )),
+                        Some(_) => (),
+                        None => seen.push((key, *action)),
+                    }
+                }
+            }
+            problems
+        }
+    }
+
+    impl KeyBindings {
+        /// Bindings for a config that has passed `GameConfig::validate`.
+        /// Unknown key names are skipped.
+        pub fn from_config(controls: &ControlsConfig) -> KeyBindings {
+            let mut bindings = KeyBindings {
+                steering: controls.steering,
+                layout: controls.layout,
+                ..Default::default()
+            };
+            for action in Action::ALL.iter() {
+                let keys = controls.keys(*action).iter().filter_map(|name| key_from_name(name)).collect();
+                bindings.keys.insert(*action, keys);
+            }
+            bindings
+        }
+
+        pub fn to_config(&self) -> ControlsConfig {
+            let mut controls = ControlsConfig { steering: self.steering, ..ControlsConfig::for_layout(self.layout) };
+            for action in Action::ALL.iter() {
+                *controls.keys_mut(*action) = self
+                    .keys_for(*action)
+                    .iter()
+                    .map(|key| key_name(*key).to_string())
+                    .collect();
+            }
+            controls
+        }
+
+        pub fn keys_for(&self, action: Action) -> &[KeyCode] {
+            self.keys.get(&action).map(|keys| keys.as_slice()).unwrap_or(&[])
+        }
+
+        /// The action `key` is bound to, if any.
+        pub fn action_for(&self, key: KeyCode) -> Option<Action> {
+            Action::ALL.iter().copied().find(|action| self.keys_for(*action).contains(&key))
+        }
+
+        pub fn pressed(&self, action: Action, input: &Input<KeyCode>) -> bool {
+            self.keys_for(action).iter().any(|key| input.pressed(*key))
+        }
+
+        pub fn just_pressed(&self, action: Action, input: &Input<KeyCode>) -> bool {
+            self.keys_for(action).iter().any(|key| input.just_pressed(*key))
+        }
+
+        /// Adds `key` to `action`, refusing keys already used by another
+        /// action and keys that cannot be saved.
+        pub fn bind(&mut self, action: Action, key: KeyCode) -> Result<(), String> {
+            if key_name(key).is_empty() {
+                return Err(format!("{:?} cannot be bound", key));
+            }
+            match self.action_for(key) {
+                Some(other) if other == action => Ok(()),
+                Some(other) => Err(format!("{} is already bound to {}", key_name(key), other.label())),
+                None => {
+                    self.keys.entry(action).or_default().push(key);
+                    Ok(())
+                }
+            }
+        }
+
+        pub fn clear(&mut self, action: Action) {
+            self.keys.insert(action, Vec::new());
+        }
+    }
+
+    /// Name of `key` in the config file, or an empty string for keys that
+    /// cannot be bound.
+    pub fn key_name(key: KeyCode) -> &'static str {
+        KEY_NAMES
+            .iter()
+            .find(|(code, _)| *code == key)
+            .map(|(_, name)| *name)
+            .unwrap_or("")
+    }
+
+    pub fn key_from_name(name: &str) -> Option<KeyCode> {
+        KEY_NAMES
+            .iter()
+            .find(|(_, known)| known.eq_ignore_ascii_case(name))
+            .map(|(code, _)| *code)
+    }
+
+    /// Handles the keys while the controls screen is open. Up/down picks
+    /// an action and Enter waits for a key to add to it. Backspace clears
+    /// the action's keys, R restores the layout's defaults and escape saves the
+    /// bindings and closes the screen.
+    pub fn edit_controls(
+        keyboard_input: &Input<KeyCode>,
+        menu: &mut ControlsMenu,
+        bindings: &mut KeyBindings,
+        config: &mut GameConfig,
+        config_path: &Path,
+    ) {
+        let action = Action::ALL[menu.cursor];
+        if menu.listening {
+            if keyboard_input.just_pressed(KeyCode::Escape) {
+                menu.listening = false;
+                menu.message = None;
+                return;
+            }
+            if let Some((key, _)) = KEY_NAMES.iter().find(|(key, _)| keyboard_input.just_pressed(*key)) {
+                menu.listening = false;
+                menu.message = bindings.bind(action, *key).err();
+            }
+            return;
+        }
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            let controls = bindings.to_config();
+            if let Some(problem) = controls.problems().first() {
+                menu.message = Some(problem.clone());
+                return;
+            }
+            config.controls = controls;
+            if let Err(err) = config.save_settings(config_path) {
+                println!("Could not save controls: {}", err);
+            }
+            menu.message = None;
+            menu.open = false;
+            return;
+        }
+        let count = Action::ALL.len();
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            menu.cursor = (menu.cursor + count - 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            menu.cursor = (menu.cursor + 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            menu.listening = true;
+            menu.message = None;
+        } else if keyboard_input.just_pressed(KeyCode::Back) {
+            bindings.clear(action);
+        } else if keyboard_input.just_pressed(KeyCode::R) {
+            *bindings = KeyBindings {
+                steering: bindings.steering,
+                ..KeyBindings::from_config(&ControlsConfig::for_layout(bindings.layout))
+            };
+            menu.message = None;
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn layout_defaults_have_no_problems() {
+            for layout in [KeyboardLayout::Qwerty, KeyboardLayout::Azerty].iter() {
+                assert!(ControlsConfig::for_layout(*layout).problems().is_empty());
+            }
+        }
+
+        #[test]
+        fn azerty_steers_with_zqsd() {
+            let controls = ControlsConfig::for_layout(KeyboardLayout::Azerty);
+            assert_eq!(controls.up, vec!["Up", "Z"]);
+            assert_eq!(controls.left, vec!["Left", "Q"]);
+            assert!(!controls.up.contains(&"W".to_string()));
+        }
+
+        #[test]
+        fn missing_keys_come_from_the_file_layout() {
+            let controls: ControlsConfig = ron::de::from_str("(layout: Azerty, up: [\"Up\"])").unwrap();
+            assert_eq!(controls.layout, KeyboardLayout::Azerty);
+            assert_eq!(controls.up, vec!["Up"]);
+            assert_eq!(controls.left, vec!["Left", "Q"]);
+        }
+
+        #[test]
+        fn bindings_keep_the_layout() {
+            let controls = ControlsConfig::for_layout(KeyboardLayout::Azerty);
+            let round_trip = KeyBindings::from_config(&controls).to_config();
+            assert_eq!(round_trip.layout, KeyboardLayout::Azerty);
+            assert_eq!(round_trip.up, controls.up);
+        }
+    }
+}
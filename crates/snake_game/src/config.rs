@@ -0,0 +1,275 @@
+pub mod config_data {
+    use serde::{Deserialize, Serialize};
+    use std::path::PathBuf;
+    use crate::game::game_data::*;
+    use crate::bindings::bindings_data::*;
+    use crate::gamepad::gamepad_data::*;
+    use crate::theme::theme_data::*;
+    use crate::effects::effects_data::*;
+
+    /// Name of the config file read at startup when no other path is
+    /// given, kept in the platform's user config directory.
+    pub const CONFIG_FILE_NAME: &str = "snake.ron";
+
+    /// Smallest board, in cells across, that leaves room for the snake to
+    /// spawn.
+    pub const MIN_BOARD_CELLS: u32 = 16;
+
+    /// Resource: game parameters loaded from a RON file. Any field left out
+    /// of the file keeps its default.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct GameConfig {
+        /// Width and height of the board in pixels.
+        pub play_area: f32,
+        pub cell_size: f64,
+        pub mode: GameMode,
+        pub difficulty: Difficulty,
+        pub tick_seconds: TickSeconds,
+        pub controls: ControlsConfig,
+        pub gamepad: GamepadConfig,
+        pub display: DisplayConfig,
+    }
+
+    /// How the board is drawn. Changed on the settings screen and saved
+    /// back to the config file.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct DisplayConfig {
+        /// Glide pieces between cells instead of jumping a cell per move.
+        pub smooth_movement: bool,
+        /// Name of the theme in `assets/themes` to draw the game with.
+        pub theme: String,
+        pub color_vision: ColorVision,
+        /// Colors to draw with in place of the theme's. Like the
+        /// color-vision palettes, which still take precedence, it sets the
+        /// theme's sprite atlas aside.
+        pub palette: Option<Palette>,
+        /// Darken every other tail segment.
+        pub tail_pattern: bool,
+        pub effects: EffectsConfig,
+    }
+
+    /// Resource: the config file that settings changed in game are saved to.
+    pub struct ConfigPath(pub PathBuf);
+
+    /// Seconds between snake moves for each difficulty.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct TickSeconds {
+        pub easy: f32,
+        pub normal: f32,
+        pub hard: f32,
+    }
+}
+
+pub mod config_functions {
+    use bevy::prelude::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use super::config_data::*;
+    use crate::game::game_data::*;
+    use crate::bindings::bindings_data::*;
+    use crate::gamepad::gamepad_data::*;
+    use crate::theme::theme_data::*;
+    use crate::effects::effects_data::*;
+
+    /// `snake.ron` in the platform's user config directory, or in the
+    /// working directory on platforms without one.
+    pub fn default_config_path() -> PathBuf {
+        dirs::config_dir()
+            .map(|dir| dir.join("snake_bevy").join(CONFIG_FILE_NAME))
+            .unwrap_or_else(|| PathBuf::from(CONFIG_FILE_NAME))
+    }
+
+    impl Default for GameConfig {
+        fn default() -> Self {
+            GameConfig {
+                play_area: 600.0,
+                cell_size: 25.0,
+                mode: GameMode::default(),
+                difficulty: Difficulty::default(),
+                tick_seconds: TickSeconds::default(),
+                controls: ControlsConfig::default(),
+                gamepad: GamepadConfig::default(),
+                display: DisplayConfig::default(),
+            }
+        }
+    }
+
+    impl Default for DisplayConfig {
+        fn default() -> Self {
+            DisplayConfig {
+                smooth_movement: true,
+                theme: "Classic".to_string(),
+                color_vision: ColorVision::default(),
+                palette: None,
+                tail_pattern: false,
+                effects: EffectsConfig::default(),
+            }
+        }
+    }
+
+    impl Default for TickSeconds {
+        fn default() -> Self {
+            TickSeconds {
+                easy: Difficulty::Easy.tick_seconds(),
+                normal: Difficulty::Normal.tick_seconds(),
+                hard: Difficulty::Hard.tick_seconds(),
+            }
+        }
+    }
+
+    impl TickSeconds {
+        pub fn get(&self, difficulty: Difficulty) -> f32 {
+            match difficulty {
+                Difficulty::Easy => self.easy,
+                Difficulty::Normal => self.normal,
+                Difficulty::Hard => self.hard,
+            }
+        }
+    }
+
+    impl GameConfig {
+        /// Reads and validates the config at `path`. A missing file gives
+        /// the defaults.
+        pub fn load(path: &Path) -> Result<GameConfig, String> {
+            if !path.exists() {
+                return Ok(GameConfig::default());
+            }
+            let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+            let config: GameConfig = ron::de::from_str(&text)
+                .map_err(|err| format!("invalid config `{}`: {}", path.display(), err))?;
+            config
+                .validate()
+                .map_err(|err| format!("invalid config `{}`: {}", path.display(), err))?;
+            Ok(config)
+        }
+
+        /// Writes the settings that can be changed in game (controls and
+        /// display) into the config file at `path`, keeping the rest of the
+        /// file's settings.
+        pub fn save_settings(&self, path: &Path) -> Result<(), String> {
+            let mut config = if path.exists() {
+                let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+                ron::de::from_str(&text).map_err(|err| format!("invalid config `{}`: {}", path.display(), err))?
+            } else {
+                GameConfig::default()
+            };
+            config.controls = self.controls.clone();
+            config.display = self.display.clone();
+            if let Some(dir) = path.parent().filter(|dir| !dir.as_os_str().is_empty()) {
+                fs::create_dir_all(dir).map_err(|err| format!("could not create `{}`: {}", dir.display(), err))?;
+            }
+            let text = ron::ser::to_string_pretty(&config, ron::ser::PrettyConfig::default())
+                .map_err(|err| err.to_string())?;
+            fs::write(path, text).map_err(|err| format!("could not write `{}`: {}", path.display(), err))
+        }
+
+        /// Checks that the board lines up with the grid, timings are
+        /// positive, the deadzone and colors are in range and every action
+        /// has its own keys, listing every problem found.
+        pub fn validate(&self) -> Result<(), String> {
+            let mut problems = Vec::new();
+            if self.cell_size <= 0.0 {
+                problems.push(format!("`cell_size` must be positive, got {}", self.cell_size));
+            }
+            if self.play_area <= 0.0 {
+                problems.push(format!("`play_area` must be positive, got {}", self.play_area));
+            }
+            if problems.is_empty() {
+                let cells = self.play_area as f64 / self.cell_size;
+                if (cells / 2.0).fract() != 0.0 {
+                    problems.push(format!(
+                        "`play_area` ({}) must be an even multiple of `cell_size` ({})",
+                        self.play_area, self.cell_size
+                    ));
+                } else if (cells as u32) < MIN_BOARD_CELLS {
+                    problems.push(format!(
+                        "the board is {} cells across, it must be at least {}",
+                        cells, MIN_BOARD_CELLS
+                    ));
+                }
+            }
+            for (name, seconds) in [
+                ("easy", self.tick_seconds.easy),
+                ("normal", self.tick_seconds.normal),
+                ("hard", self.tick_seconds.hard),
+            ]
+            .iter()
+            {
+                if *seconds <= 0.0 {
+                    problems.push(format!("`tick_seconds.{}` must be positive, got {}", name, seconds));
+                }
+            }
+            if self.gamepad.deadzone < 0.0 || self.gamepad.deadzone >= 1.0 {
+                problems.push(format!("`gamepad.deadzone` must be at least 0 and below 1, got {}", self.gamepad.deadzone));
+            }
+            problems.extend(self.controls.problems());
+            if let Some(palette) = &self.display.palette {
+                problems.extend(palette.problems("display.palette"));
+            }
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(problems.join("; "))
+            }
+        }
+    }
+
+    pub fn rgb(color: [f32; 3]) -> Color {
+        Color::rgb(color[0], color[1], color[2])
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn problems(config: &GameConfig) -> Vec<String> {
+            match config.validate() {
+                Ok(()) => Vec::new(),
+                Err(err) => err.split("; ").map(String::from).collect(),
+            }
+        }
+
+        #[test]
+        fn the_defaults_and_the_example_file_are_valid() {
+            assert_eq!(problems(&GameConfig::default()), Vec::<String>::new());
+            let example: GameConfig =
+                ron::de::from_str(include_str!("../../../snake.example.ron")).expect("snake.example.ron parses");
+            assert_eq!(problems(&example), Vec::<String>::new());
+        }
+
+        #[test]
+        fn the_board_must_line_up_with_the_grid() {
+            let odd = GameConfig { play_area: 625.0, ..GameConfig::default() };
+            assert_eq!(problems(&odd).len(), 1);
+            let small = GameConfig { play_area: 350.0, ..GameConfig::default() };
+            assert!(problems(&small)[0].contains("at least"));
+            let negative = GameConfig { cell_size: -25.0, ..GameConfig::default() };
+            assert!(problems(&negative)[0].contains("`cell_size`"));
+        }
+
+        #[test]
+        fn every_problem_is_listed() {
+            let mut config = GameConfig::default();
+            config.tick_seconds.hard = 0.0;
+            config.gamepad.deadzone = 1.0;
+            config.controls.pause = vec![config.controls.up[0].clone()];
+            config.display.palette = Some(Palette {
+                background: [0.0, 0.0, 0.0],
+                wall: [0.5, 0.5, 0.5],
+                snake: [0.0, 2.0, 0.0],
+                tail: [0.0, 0.5, 0.0],
+                fruit: [1.0, 0.0, 0.0],
+                text: [1.0, 1.0, 1.0],
+            });
+            let found = problems(&config);
+            assert_eq!(found.len(), 4, "{:?}", found);
+            assert!(found.iter().any(|problem| problem.contains("`tick_seconds.hard`")));
+            assert!(found.iter().any(|problem| problem.contains("`gamepad.deadzone`")));
+            assert!(found.iter().any(|problem| problem.contains("binds")));
+            assert!(found.iter().any(|problem| problem.contains("`display.palette.snake`")));
+        }
+    }
+}
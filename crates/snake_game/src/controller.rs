@@ -0,0 +1,347 @@
+pub mod controller_data {
+    use bevy::prelude::*;
+    use crate::snake::snake_data::*;
+
+    /// Everything a controller is allowed to see when picking its next move.
+    #[derive(Clone, Debug)]
+    pub struct BoardView {
+        pub grid_max: f32,
+        pub head: Vec2,
+        pub direction: SnakeDirection,
+        pub tail: Vec<Vec2>,
+        /// Other snakes on the board, each listed head first.
+        pub opponents: Vec<Vec<Vec2>>,
+        pub obstacles: Vec<Vec2>,
+        pub fruit: Option<Vec2>,
+        pub tick: u32,
+    }
+
+    /// Something that steers a snake: a built-in AI, an external bot or a
+    /// trained genome.
+    pub trait Controller: Send + Sync {
+        fn name(&self) -> String;
+        fn next_move(&mut self, view: &BoardView) -> SnakeDirection;
+        /// Called before every new game.
+        fn reset(&mut self) {}
+    }
+
+    /// Resource: when set, steers the player's snake in place of the keyboard.
+    #[derive(Default)]
+    pub struct Pilot(pub Option<Box<dyn Controller>>);
+
+    /// Heads for the fruit along the shortest axis, avoiding any move that
+    /// would kill it on the next tick.
+    pub struct GreedyController;
+
+    /// Picks a random move that does not kill it on the next tick.
+    pub struct RandomController {
+        pub seed: u64,
+        pub rng: rand::rngs::StdRng,
+    }
+
+    /// A bot running as a separate process, spoken to over stdin/stdout.
+    ///
+    /// Every tick the bot is sent one line:
+    ///
+    /// `tick <n> grid <grid_max> head <x> <y> dir <UP|DOWN|LEFT|RIGHT> fruit <x> <y>|none tail <x>,<y>;... opponents <x>,<y>;...|... obstacles <x>,<y>;...`
+    ///
+    /// and must answer with a single line containing `UP`, `DOWN`, `LEFT` or
+    /// `RIGHT`. Anything else keeps the current direction.
+    pub struct ExternalController {
+        pub command: String,
+        pub child: std::process::Child,
+        pub stdout: std::io::BufReader<std::process::ChildStdout>,
+    }
+}
+
+pub mod controller_functions {
+    use bevy::prelude::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use std::io::{BufRead, Write};
+    use std::process::{Command, Stdio};
+    use super::controller_data::*;
+    use std::path::Path;
+    use crate::game::game_data::*;
+    use crate::sim::sim_data::*;
+    use crate::snake::snake_data::*;
+    use crate::snake::snake_functions::*;
+    use crate::state::state_data::*;
+    use crate::training::training_data::*;
+    use crate::training::training_functions::*;
+    use crate::level::level_data::*;
+
+    impl BoardView {
+        /// The view of the board from snake `index` of a `Simulation`.
+        pub fn from_sim(sim: &Simulation, index: usize) -> BoardView {
+            let snake = &sim.snakes[index];
+            BoardView {
+                grid_max: sim.grid_max,
+                head: snake.position,
+                direction: snake.direction,
+                tail: snake.tail.clone(),
+                opponents: sim
+                    .snakes
+                    .iter()
+                    .enumerate()
+                    .filter(|(i, other)| *i != index && other.alive)
+                    .map(|(_, other)| {
+                        let mut body = vec![other.position];
+                        body.extend(other.tail.iter().cloned());
+                        body
+                    })
+                    .collect(),
+                obstacles: sim.obstacles.clone(),
+                fruit: sim.fruit,
+                tick: sim.tick,
+            }
+        }
+
+        /// The view of the board from the player's `Snake` in the running game.
+        pub fn from_game(
+            game: &GameState,
+            level: &Level,
+            snake: &Snake,
+            tail: &[Vec2],
+            fruit: Option<Vec2>
+        ) -> BoardView {
+            BoardView {
+                grid_max: grid_max(game.play_area, game.cell_size),
+                head: snake.position,
+                direction: snake.direction,
+                tail: tail.to_vec(),
+                opponents: Vec::new(),
+                obstacles: level.cells(),
+                fruit,
+                tick: 0,
+            }
+        }
+
+        /// True if moving onto `position` would end the game for this snake.
+        pub fn is_blocked(&self, position: Vec2) -> bool {
+            hits_wall(position, self.grid_max)
+                || self.tail.contains(&position)
+                || self.opponents.iter().flatten().any(|segment| *segment == position)
+                || self.obstacles.contains(&position)
+        }
+
+        /// Moves that neither reverse into the tail nor hit anything next tick.
+        pub fn safe_moves(&self) -> Vec<SnakeDirection> {
+            SnakeDirection::ALL
+                .iter()
+                .cloned()
+                .filter(|direction| *direction != self.direction.opposite())
+                .filter(|direction| !self.is_blocked(direction.step(self.head)))
+                .collect()
+        }
+    }
+
+    impl Controller for GreedyController {
+        fn name(&self) -> String {
+            "greedy".to_string()
+        }
+
+        fn next_move(&mut self, view: &BoardView) -> SnakeDirection {
+            let safe = view.safe_moves();
+            let fruit = match view.fruit {
+                Some(fruit) => fruit,
+                None => return safe.first().cloned().unwrap_or(view.direction),
+            };
+            let distance = |direction: &SnakeDirection| {
+                let next = direction.step(view.head);
+                (next.x() - fruit.x()).abs() + (next.y() - fruit.y()).abs()
+            };
+            let mut best: Option<SnakeDirection> = None;
+            for direction in safe {
+                best = match best {
+                    Some(current) if distance(&current) <= distance(&direction) => Some(current),
+                    _ => Some(direction),
+                };
+            }
+            best.unwrap_or(view.direction)
+        }
+    }
+
+    impl RandomController {
+        pub fn new(seed: u64) -> RandomController {
+            RandomController { seed, rng: StdRng::seed_from_u64(seed) }
+        }
+    }
+
+    impl Controller for RandomController {
+        fn name(&self) -> String {
+            format!("random:{}", self.seed)
+        }
+
+        fn next_move(&mut self, view: &BoardView) -> SnakeDirection {
+            let safe = view.safe_moves();
+            if safe.is_empty() {
+                return view.direction;
+            }
+            safe[self.rng.gen_range(0, safe.len())]
+        }
+
+        fn reset(&mut self) {
+            self.rng = StdRng::seed_from_u64(self.seed);
+        }
+    }
+
+    impl ExternalController {
+        /// Starts `command` (program followed by its arguments).
+        pub fn spawn(command: &str) -> Result<ExternalController, String> {
+            let mut parts = command.split_whitespace();
+            let program = parts.next().ok_or_else(|| "empty bot command".to_string())?;
+            let mut child = Command::new(program)
+                .args(parts)
+                .stdin(Stdio::piped())
+                .stdout(Stdio::piped())
+                .spawn()
+                .map_err(|err| format!("could not start bot `{}`: {}", command, err))?;
+            let stdout = child
+                .stdout
+                .take()
+                .ok_or_else(|| format!("bot `{}` has no stdout", command))?;
+            Ok(ExternalController {
+                command: command.to_string(),
+                child,
+                stdout: std::io::BufReader::new(stdout),
+            })
+        }
+    }
+
+    impl Controller for ExternalController {
+        fn name(&self) -> String {
+            self.command.clone()
+        }
+
+        fn next_move(&mut self, view: &BoardView) -> SnakeDirection {
+            let stdin = match self.child.stdin.as_mut() {
+                Some(stdin) => stdin,
+                None => return view.direction,
+            };
+            if writeln!(stdin, "{}", encode_view(view)).and_then(|_| stdin.flush()).is_err() {
+                return view.direction;
+            }
+            let mut line = String::new();
+            match self.stdout.read_line(&mut line) {
+                Ok(read) if read > 0 => parse_direction(line.trim()).unwrap_or(view.direction),
+                _ => view.direction,
+            }
+        }
+    }
+
+    impl Drop for ExternalController {
+        fn drop(&mut self) {
+            let _ = self.child.kill();
+            let _ = self.child.wait();
+        }
+    }
+
+    pub fn direction_name(direction: SnakeDirection) -> &'static str {
+        match direction {
+            SnakeDirection::UP => "UP",
+            SnakeDirection::DOWN => "DOWN",
+            SnakeDirection::LEFT => "LEFT",
+            SnakeDirection::RIGHT => "RIGHT",
+        }
+    }
+
+    pub fn parse_direction(name: &str) -> Option<SnakeDirection> {
+        match name.to_uppercase().as_str() {
+            "UP" | "U" => Some(SnakeDirection::UP),
+            "DOWN" | "D" => Some(SnakeDirection::DOWN),
+            "LEFT" | "L" => Some(SnakeDirection::LEFT),
+            "RIGHT" | "R" => Some(SnakeDirection::RIGHT),
+            _ => None,
+        }
+    }
+
+    fn encode_cells<'a>(cells: impl Iterator<Item = &'a Vec2>) -> String {
+        cells
+            .map(|cell| format!("{},{}", cell.x(), cell.y()))
+            .collect::<Vec<String>>()
+            .join(";")
+    }
+
+    /// One line of the `ExternalController` protocol.
+    pub fn encode_view(view: &BoardView) -> String {
+        let fruit = match view.fruit {
+            Some(fruit) => format!("{} {}", fruit.x(), fruit.y()),
+            None => "none".to_string(),
+        };
+        let opponents = view
+            .opponents
+            .iter()
+            .map(|body| encode_cells(body.iter()))
+            .collect::<Vec<String>>()
+            .join("|");
+        format!(
+            "tick {} grid {} head {} {} dir {} fruit {} tail {} opponents {} obstacles {}",
+            view.tick,
+            view.grid_max,
+            view.head.x(),
+            view.head.y(),
+            direction_name(view.direction),
+            fruit,
+            encode_cells(view.tail.iter()),
+            opponents,
+            encode_cells(view.obstacles.iter()),
+        )
+    }
+
+    /// Builds a controller from a command-line spec: `greedy`, `random`,
+    /// `random:<seed>`, `genome:<path>` or `cmd:<program and arguments>`.
+    pub fn controller_from_spec(spec: &str) -> Result<Box<dyn Controller>, String> {
+        if let Some(command) = spec.strip_prefix("cmd:") {
+            return Ok(Box::new(ExternalController::spawn(command)?));
+        }
+        if let Some(path) = spec.strip_prefix("genome:") {
+            let genome = load_genome(Path::new(path))?;
+            return Ok(Box::new(GenomeController { name: spec.to_string(), genome }));
+        }
+        let mut parts = spec.splitn(2, ':');
+        match (parts.next(), parts.next()) {
+            (Some("greedy"), None) => Ok(Box::new(GreedyController)),
+            (Some("random"), None) => Ok(Box::new(RandomController::new(0))),
+            (Some("random"), Some(seed)) => seed
+                .parse()
+                .map(|seed| Box::new(RandomController::new(seed)) as Box<dyn Controller>)
+                .map_err(|_| format!("invalid seed in controller `{}`", spec)),
+            _ => Err(format!("unknown controller `{}`", spec)),
+        }
+    }
+
+    /// Grid cell of the fruit currently on the board, if any.
+    pub fn fruit_cell(fruit_query: &Query<&Fruit>) -> Option<Vec2> {
+        fruit_query.iter().next().map(|fruit| fruit.position)
+    }
+
+    /// Lets the `Pilot` choose the snake's direction right after each tick,
+    /// from the position it just moved to.
+    pub fn pilot_steering(
+        timer: Res<GameTimer>,
+        game: Res<GameState>,
+        flow: Res<GameFlow>,
+        level: Res<Level>,
+        mut pilot: ResMut<Pilot>,
+        mut snake_query: Query<&mut Snake>,
+        tail_query: Query<&Tail>,
+        fruit_query: Query<&Fruit>,
+    ) {
+        if !timer.0.finished || !flow.is(AppState::Playing) {
+            return;
+        }
+        let controller = match pilot.0.as_mut() {
+            Some(controller) => controller,
+            None => return,
+        };
+        let fruit = fruit_cell(&fruit_query);
+        let tail: Vec<Vec2> = tail_cells(&tail_query);
+        for mut snake in snake_query.iter_mut() {
+            let view = BoardView::from_game(&game, &level, &snake, &tail, fruit);
+            let next_move = controller.next_move(&view);
+            if next_move != snake.direction.opposite() {
+                snake.direction = next_move;
+            }
+        }
+    }
+}
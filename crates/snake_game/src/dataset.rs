@@ -0,0 +1,284 @@
+pub mod dataset_data {
+    use std::path::PathBuf;
+    use crate::snake::snake_data::*;
+
+    /// Cells on each side of the head included in the observation window.
+    pub const OBSERVATION_RADIUS: i32 = 3;
+
+    /// Resource: collects what the player saw and did each tick so human
+    /// games can be used to train models. Disabled when `path` is `None`.
+    #[derive(Default)]
+    pub struct DatasetRecorder {
+        pub path: Option<PathBuf>,
+        /// Moves committed by `snake_movement` this frame, waiting for
+        /// `record_dataset` to attach an observation.
+        pub committed: Vec<SnakeDirection>,
+        /// What each snake saw on the previous frame, before the move it
+        /// committed this frame was made.
+        pub last_observations: Vec<Vec<f32>>,
+        pub samples: Vec<DatasetSample>,
+        pub game_id: u128,
+        pub score: usize,
+    }
+
+    pub struct DatasetSample {
+        pub observation: Vec<f32>,
+        pub action: SnakeDirection,
+        pub score: usize,
+    }
+}
+
+pub mod dataset_functions {
+    use bevy::prelude::*;
+    use std::fs::OpenOptions;
+    use std::io::Write;
+    use std::path::PathBuf;
+    use std::time::{SystemTime, UNIX_EPOCH};
+    use super::dataset_data::*;
+    use crate::controller::controller_data::*;
+    use crate::controller::controller_functions::*;
+    use crate::game::game_data::*;
+    use crate::snake::snake_data::*;
+    use crate::snake::snake_functions::*;
+    use crate::state::state_data::*;
+    use crate::level::level_data::*;
+
+    impl DatasetRecorder {
+        pub fn new(path: Option<PathBuf>) -> DatasetRecorder {
+            DatasetRecorder { path, ..Default::default() }
+        }
+
+        pub fn enabled(&self) -> bool {
+            self.path.is_some()
+        }
+
+        /// Called by `snake_movement` with the direction each snake moves
+        /// in on a tick.
+        pub fn commit(&mut self, direction: SnakeDirection) {
+            if self.enabled() {
+                self.committed.push(direction);
+            }
+        }
+
+        /// Pairs the moves committed since the last call with what each
+        /// snake saw before making them, then keeps `observations` for the
+        /// next moves.
+        pub fn record_tick(&mut self, score: usize, observations: Vec<Vec<f32>>) {
+            let committed: Vec<SnakeDirection> = self.committed.drain(..).collect();
+            if !committed.is_empty() {
+                if self.samples.is_empty() {
+                    self.game_id = SystemTime::now()
+                        .duration_since(UNIX_EPOCH)
+                        .map(|time| time.as_millis())
+                        .unwrap_or(0);
+                }
+                self.score = score;
+                let last_observations: Vec<Vec<f32>> = self.last_observations.drain(..).collect();
+                for (observation, action) in last_observations.into_iter().zip(committed) {
+                    self.samples.push(DatasetSample { observation, action, score });
+                }
+            }
+            self.last_observations = observations;
+        }
+
+        /// Forgets a round that ended without a game over, so its moves are
+        /// not written under the next game.
+        pub fn discard_round(&mut self) {
+            self.committed.clear();
+            self.last_observations.clear();
+            self.samples.clear();
+            self.score = 0;
+        }
+    }
+
+    /// Column names for `observation`, in order.
+    pub fn observation_header() -> Vec<String> {
+        let mut header: Vec<String> = vec![
+            "head_x", "head_y", "dir_up", "dir_down", "dir_left", "dir_right", "fruit_dx", "fruit_dy",
+            "danger_up", "danger_down", "danger_left", "danger_right", "length",
+        ]
+        .into_iter()
+        .map(String::from)
+        .collect();
+        for dy in -OBSERVATION_RADIUS..=OBSERVATION_RADIUS {
+            for dx in -OBSERVATION_RADIUS..=OBSERVATION_RADIUS {
+                header.push(format!("cell_{}_{}", dx, dy));
+            }
+        }
+        header
+    }
+
+    /// What the player could see: head position and heading, where the
+    /// fruit is relative to the head, which neighbouring cells are deadly,
+    /// the snake's length and a window of blocked cells around the head.
+    pub fn observation(view: &BoardView) -> Vec<f32> {
+        let one_hot = |on: bool| if on { 1.0 } else { 0.0 };
+        let mut observation = vec![view.head.x(), view.head.y()];
+        for direction in SnakeDirection::ALL.iter() {
+            observation.push(one_hot(view.direction == *direction));
+        }
+        match view.fruit {
+            Some(fruit) => {
+                observation.push(fruit.x() - view.head.x());
+                observation.push(fruit.y() - view.head.y());
+            }
+            None => observation.extend(&[0.0, 0.0]),
+        }
+        for direction in SnakeDirection::ALL.iter() {
+            observation.push(one_hot(view.is_blocked(direction.step(view.head))));
+        }
+        observation.push((view.tail.len() + 1) as f32);
+        for dy in -OBSERVATION_RADIUS..=OBSERVATION_RADIUS {
+            for dx in -OBSERVATION_RADIUS..=OBSERVATION_RADIUS {
+                let cell = Vec2::new(view.head.x() + dx as f32, view.head.y() + dy as f32);
+                observation.push(one_hot(view.is_blocked(cell)));
+            }
+        }
+        observation
+    }
+
+    /// Pairs each move committed this frame with an observation of the
+    /// board taken the frame before, just ahead of the move. Games flown by
+    /// a `Pilot` are not recorded. The last observation is kept while the
+    /// round is paused, so the first move after it is not lost.
+    pub fn record_dataset(
+        game: Res<GameState>,
+        flow: Res<GameFlow>,
+        level: Res<Level>,
+        pilot: Res<Pilot>,
+        mut recorder: ResMut<DatasetRecorder>,
+        snake_query: Query<&Snake>,
+        tail_query: Query<&Tail>,
+        fruit_query: Query<&Fruit>,
+    ) {
+        if !recorder.enabled() || flow.is_paused() {
+            return;
+        }
+        if pilot.0.is_some() || !flow.is(AppState::Playing) {
+            recorder.committed.clear();
+            recorder.last_observations.clear();
+            return;
+        }
+        let fruit = fruit_cell(&fruit_query);
+        let tail: Vec<Vec2> = tail_cells(&tail_query);
+        let observations = snake_query
+            .iter()
+            .map(|snake| observation(&BoardView::from_game(&game, &level, snake, &tail, fruit)))
+            .collect();
+        recorder.record_tick(game.score, observations);
+    }
+
+    /// Drops the unfinished round when it is restarted or left for the
+    /// title screen.
+    pub fn reset_dataset(
+        mut restart_reader: Local<EventReader<EventRestart>>,
+        restart_event: Res<Events<EventRestart>>,
+        mut state_reader: Local<EventReader<EventStateChange>>,
+        state_events: Res<Events<EventStateChange>>,
+        mut recorder: ResMut<DatasetRecorder>,
+    ) {
+        let restarted = restart_reader.iter(&restart_event).next().is_some();
+        let quit = state_reader.iter(&state_events).any(|change| change.entered(AppState::Title));
+        if restarted || quit {
+            recorder.discard_round();
+        }
+    }
+
+    /// Appends the finished game to the dataset file, labelling every
+    /// sample with the game's outcome.
+    pub fn flush_dataset(
+        mut game_over_reader: Local<EventReader<EventGameOver>>,
+        game_over_event: Res<Events<EventGameOver>>,
+        mut recorder: ResMut<DatasetRecorder>,
+    ) {
+        for _ in game_over_reader.iter(&game_over_event) {
+            if recorder.samples.is_empty() {
+                continue;
+            }
+            if let Err(err) = write_game(&recorder) {
+                println!("Could not write dataset: {}", err);
+            }
+            recorder.samples.clear();
+            recorder.score = 0;
+        }
+    }
+
+    /// CSV with one row per tick: game id, tick, the observation columns,
+    /// the chosen direction, then the outcome (final score, score gained
+    /// after this tick and ticks left before the snake died).
+    fn write_game(recorder: &DatasetRecorder) -> std::io::Result<()> {
+        let path = match &recorder.path {
+            Some(path) => path,
+            None => return Ok(()),
+        };
+        let mut file = OpenOptions::new().create(true).append(true).open(path)?;
+        if file.metadata()?.len() == 0 {
+            writeln!(
+                file,
+                "game,tick,{},action,final_score,score_gained,ticks_left",
+                observation_header().join(",")
+            )?;
+        }
+        let ticks = recorder.samples.len();
+        for (tick, sample) in recorder.samples.iter().enumerate() {
+            let observation: Vec<String> = sample.observation.iter().map(|value| value.to_string()).collect();
+            writeln!(
+                file,
+                "{},{},{},{},{},{},{}",
+                recorder.game_id,
+                tick,
+                observation.join(","),
+                direction_name(sample.action),
+                recorder.score,
+                recorder.score - sample.score,
+                ticks - tick - 1,
+            )?;
+        }
+        Ok(())
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn recording() -> DatasetRecorder {
+            DatasetRecorder::new(Some(PathBuf::from("dataset.csv")))
+        }
+
+        /// One tick: the move made, then what the board looked like after it.
+        fn tick(recorder: &mut DatasetRecorder, action: SnakeDirection, score: usize, seen: f32) {
+            recorder.commit(action);
+            recorder.record_tick(score, vec![vec![seen]]);
+        }
+
+        #[test]
+        fn moves_are_paired_with_the_observation_before_them() {
+            let mut recorder = recording();
+            recorder.record_tick(0, vec![vec![1.0]]);
+            tick(&mut recorder, SnakeDirection::UP, 0, 2.0);
+            tick(&mut recorder, SnakeDirection::LEFT, 1, 3.0);
+            let pairs: Vec<(Vec<f32>, SnakeDirection)> =
+                recorder.samples.iter().map(|sample| (sample.observation.clone(), sample.action)).collect();
+            assert_eq!(pairs, vec![(vec![1.0], SnakeDirection::UP), (vec![2.0], SnakeDirection::LEFT)]);
+            assert_eq!(recorder.score, 1);
+        }
+
+        #[test]
+        fn a_quit_round_is_not_carried_into_the_next_game() {
+            let mut recorder = recording();
+            recorder.record_tick(0, vec![vec![1.0]]);
+            tick(&mut recorder, SnakeDirection::UP, 4, 2.0);
+            recorder.commit(SnakeDirection::LEFT);
+            recorder.discard_round();
+            assert!(recorder.samples.is_empty());
+            assert_eq!(recorder.score, 0);
+
+            recorder.record_tick(0, vec![vec![10.0]]);
+            tick(&mut recorder, SnakeDirection::RIGHT, 0, 11.0);
+            assert_eq!(recorder.samples.len(), 1);
+            assert_eq!(recorder.samples[0].observation, vec![10.0]);
+            assert_eq!(recorder.samples[0].action, SnakeDirection::RIGHT);
+            assert_eq!(recorder.score, 0);
+        }
+    }
+}
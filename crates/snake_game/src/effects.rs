@@ -0,0 +1,256 @@
+pub mod effects_data {
+    use bevy::prelude::*;
+    use serde::{Deserialize, Serialize};
+
+    pub const BURST_PARTICLES: usize = 12;
+    /// Pixels per second a particle starts out at, at most.
+    pub const PARTICLE_SPEED: f32 = 140.0;
+    pub const PARTICLE_SECONDS: f32 = 0.5;
+    /// Seconds between one segment bursting and the next when the snake dies.
+    pub const DEATH_SEGMENT_DELAY: f32 = 0.04;
+    pub const SHAKE_SECONDS: f32 = 0.4;
+    /// How far the camera moves at the start of a shake.
+    pub const SHAKE_PIXELS: f32 = 8.0;
+    pub const FLASH_SECONDS: f32 = 0.3;
+    /// Depth of particles and the flash, in front of the board.
+    pub const PARTICLE_Z: f32 = 0.1;
+    pub const FLASH_Z: f32 = 0.5;
+
+    /// Which effects play. Each can be turned off on the settings screen.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct EffectsConfig {
+        /// Particles from a fruit as it is eaten.
+        pub fruit_burst: bool,
+        /// The snake bursts apart segment by segment when it dies.
+        pub death_explosion: bool,
+        /// The board shakes when the round ends.
+        pub screen_shake: bool,
+        /// The screen flashes white when the round ends.
+        pub flash: bool,
+    }
+
+    /// A square that flies off and shrinks away.
+    pub struct Particle {
+        pub velocity: Vec2,
+        pub age: f32,
+        pub size: f32,
+    }
+
+    /// The white overlay shown on game over.
+    pub struct Flash {
+        pub material: Handle<ColorMaterial>,
+        pub age: f32,
+    }
+
+    /// A burst of particles waiting to go off.
+    pub struct PendingBurst {
+        pub delay: f32,
+        pub cell: Vec2,
+        pub color: [f32; 3],
+    }
+
+    /// Resource: effects in progress.
+    #[derive(Default)]
+    pub struct EffectsState {
+        pub pending: Vec<PendingBurst>,
+        /// Seconds of screen shake left.
+        pub shake: f32,
+        /// Cells of the snake's tail as of the frame before, head end
+        /// first, so the death explosion can follow them once the round is
+        /// cleared away. Empty between rounds.
+        pub tail: Vec<Vec2>,
+    }
+}
+
+pub mod effects_functions {
+    use bevy::prelude::*;
+    use std::f32::consts::PI;
+    use super::effects_data::*;
+    use crate::snake::snake_data::*;
+    use crate::snake::snake_functions::*;
+    use crate::game::game_data::*;
+    use crate::config::config_data::*;
+    use crate::config::config_functions::*;
+    use crate::events::events_data::*;
+    use crate::render::game_render::*;
+    use crate::theme::theme_data::*;
+    use crate::state::state_data::*;
+
+    impl Default for EffectsConfig {
+        fn default() -> Self {
+            EffectsConfig {
+                fruit_burst: true,
+                death_explosion: true,
+                screen_shake: true,
+                flash: true,
+            }
+        }
+    }
+
+    /// Starts effects for the gameplay events of this frame: a burst where
+    /// fruit was eaten, a burst per segment when the snake dies, and a
+    /// shake and flash once the round has ended.
+    pub fn trigger_effects(
+        mut commands: Commands,
+        mut fruit_reader: Local<EventReader<EventFruitEaten>>,
+        fruit_events: Res<Events<EventFruitEaten>>,
+        mut died_reader: Local<EventReader<EventSnakeDied>>,
+        died_events: Res<Events<EventSnakeDied>>,
+        mut ended_reader: Local<EventReader<EventRoundEnded>>,
+        ended_events: Res<Events<EventRoundEnded>>,
+        config: Res<GameConfig>,
+        themes: Res<Themes>,
+        mut state: ResMut<EffectsState>,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+        tail_query: Query<&Tail>,
+    ) {
+        let effects = &config.display.effects;
+        let palette = themes.look(config.display.color_vision).palette;
+        for event in fruit_reader.iter(&fruit_events) {
+            if effects.fruit_burst {
+                state.pending.push(PendingBurst { delay: 0.0, cell: event.position, color: palette.fruit });
+            }
+        }
+        for event in died_reader.iter(&died_events) {
+            if effects.death_explosion {
+                state.pending.push(PendingBurst { delay: 0.0, cell: event.position, color: palette.snake });
+                let tail = state.tail.clone();
+                for (i, cell) in tail.into_iter().enumerate() {
+                    state.pending.push(PendingBurst {
+                        delay: (i + 1) as f32 * DEATH_SEGMENT_DELAY,
+                        cell,
+                        color: palette.tail,
+                    });
+                }
+            }
+        }
+        for _ in ended_reader.iter(&ended_events) {
+            if effects.screen_shake {
+                state.shake = SHAKE_SECONDS;
+            }
+            if effects.flash {
+                let material = materials.add(Color::rgba(1.0, 1.0, 1.0, 0.8).into());
+                commands
+                    .spawn(SpriteComponents {
+                        material: material.clone(),
+                        transform: Transform::from_translation(Vec3::new(0.0, 0.0, FLASH_Z)),
+                        sprite: Sprite::new(Vec2::new(4096.0, 4096.0)),
+                        ..Default::default()
+                    })
+                    .with(Flash { material, age: 0.0 });
+            }
+        }
+        state.tail = tail_cells(&tail_query);
+    }
+
+    /// Sets off the bursts whose delay is up.
+    pub fn spawn_bursts(
+        mut commands: Commands,
+        time: Res<Time>,
+        game: Res<GameState>,
+        flow: Res<GameFlow>,
+        mut state: ResMut<EffectsState>,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+    ) {
+        if flow.is_paused() {
+            return;
+        }
+        let size = game.cell_size as f32 * 0.3;
+        let mut waiting = Vec::new();
+        for mut burst in state.pending.drain(..) {
+            burst.delay -= time.delta_seconds;
+            if burst.delay > 0.0 {
+                waiting.push(burst);
+                continue;
+            }
+            let material = materials.add(rgb(burst.color).into());
+            let mut origin = snake_pos_to_translation(burst.cell, game.cell_size);
+            origin.set_z(PARTICLE_Z);
+            for i in 0..BURST_PARTICLES {
+                let angle = i as f32 / BURST_PARTICLES as f32 * 2.0 * PI + rand::random::<f32>() * 0.5;
+                let speed = PARTICLE_SPEED * (0.5 + rand::random::<f32>() * 0.5);
+                commands
+                    .spawn(SpriteComponents {
+                        material: material.clone(),
+                        transform: Transform::from_translation(origin),
+                        sprite: Sprite::new(Vec2::new(size, size)),
+                        ..Default::default()
+                    })
+                    .with(Particle {
+                        velocity: Vec2::new(angle.cos(), angle.sin()) * speed,
+                        age: 0.0,
+                        size,
+                    });
+            }
+        }
+        state.pending = waiting;
+    }
+
+    /// Moves particles along and shrinks them away.
+    pub fn update_particles(
+        mut commands: Commands,
+        time: Res<Time>,
+        flow: Res<GameFlow>,
+        mut query: Query<(Entity, &mut Particle, &mut Transform, &mut Sprite)>,
+    ) {
+        if flow.is_paused() {
+            return;
+        }
+        for (entity, mut particle, mut transform, mut sprite) in query.iter_mut() {
+            particle.age += time.delta_seconds;
+            if particle.age >= PARTICLE_SECONDS {
+                commands.despawn(entity);
+                continue;
+            }
+            let step = particle.velocity * time.delta_seconds;
+            transform.translation += Vec3::new(step.x(), step.y(), 0.0);
+            let size = particle.size * (1.0 - particle.age / PARTICLE_SECONDS);
+            sprite.size = Vec2::new(size, size);
+        }
+    }
+
+    /// Fades the game over flash out.
+    pub fn update_flash(
+        mut commands: Commands,
+        time: Res<Time>,
+        flow: Res<GameFlow>,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+        mut query: Query<(Entity, &mut Flash)>,
+    ) {
+        if flow.is_paused() {
+            return;
+        }
+        for (entity, mut flash) in query.iter_mut() {
+            flash.age += time.delta_seconds;
+            if flash.age >= FLASH_SECONDS {
+                commands.despawn(entity);
+                continue;
+            }
+            if let Some(material) = materials.get_mut(&flash.material) {
+                material.color = Color::rgba(1.0, 1.0, 1.0, 0.8 * (1.0 - flash.age / FLASH_SECONDS));
+            }
+        }
+    }
+
+    /// Jolts the board camera about while a shake lasts, settling as it
+    /// runs out. Effects hold still while the game is paused.
+    pub fn shake_camera(
+        time: Res<Time>,
+        mut state: ResMut<EffectsState>,
+        flow: Res<GameFlow>,
+        mut query: Query<(&BoardCamera, &mut Transform)>,
+    ) {
+        if state.shake <= 0.0 || flow.is_paused() {
+            return;
+        }
+        state.shake = (state.shake - time.delta_seconds).max(0.0);
+        let strength = SHAKE_PIXELS * state.shake / SHAKE_SECONDS;
+        for (_, mut transform) in query.iter_mut() {
+            let x = (rand::random::<f32>() * 2.0 - 1.0) * strength;
+            let y = (rand::random::<f32>() * 2.0 - 1.0) * strength;
+            transform.translation.set_x(x);
+            transform.translation.set_y(y);
+        }
+    }
+}
@@ -0,0 +1,91 @@
+pub mod events_data {
+    use bevy::prelude::*;
+    use std::collections::HashMap;
+    use crate::snake::snake_data::*;
+    use crate::game::game_data::*;
+
+    // Events sent for other plugins to react to (sound, analytics,
+    // achievements). The game rules never read them, so sending them has no
+    // effect on play; the render plugin's effects are driven by them. Read
+    // them with a `Local<EventReader<T>>` as usual.
+
+    /// Sent when a snake eats a fruit.
+    pub struct EventFruitEaten {
+        pub snake: Entity,
+        pub kind: FruitKind,
+        /// Grid cell the fruit was on.
+        pub position: Vec2,
+        /// Moves made since the round started.
+        pub tick: u32,
+    }
+
+    /// Sent whenever the score changes, including the reset to zero when a
+    /// round ends.
+    pub struct EventScoreChanged {
+        pub previous: usize,
+        pub score: usize,
+    }
+
+    /// Sent when a snake moves in a different direction than on its
+    /// previous move, whoever steered it.
+    pub struct EventSnakeTurned {
+        pub snake: Entity,
+        pub from: SnakeDirection,
+        pub to: SnakeDirection,
+        /// Grid cell the snake turned into.
+        pub position: Vec2,
+        pub tick: u32,
+    }
+
+    /// Sent when a snake dies, before the round is cleared away.
+    pub struct EventSnakeDied {
+        pub snake: Entity,
+        pub cause: DeathCause,
+        /// Grid cell the snake's head was in.
+        pub position: Vec2,
+        /// Snake length, head included.
+        pub length: usize,
+        pub tick: u32,
+    }
+
+    /// Sent when a new round begins.
+    pub struct EventRoundStarted {
+        /// Seed for this round's fruit placement.
+        pub seed: u64,
+        pub mode: GameMode,
+        pub difficulty: Difficulty,
+    }
+
+    /// Sent once a round is over, with what the game over screen shows.
+    pub struct EventRoundEnded {
+        pub score: usize,
+        pub length: usize,
+        /// Seconds survived.
+        pub duration: f32,
+        pub fruit_eaten: HashMap<FruitKind, usize>,
+        pub cause: DeathCause,
+        /// True if the score beat the best on the leaderboard.
+        pub record: bool,
+    }
+
+    /// Sent at startup once the level is in place.
+    pub struct EventLevelLoaded {
+        pub name: String,
+        /// Number of cells blocked by obstacles.
+        pub obstacles: usize,
+    }
+}
+
+pub mod events_functions {
+    use bevy::prelude::*;
+    use super::events_data::*;
+    use crate::level::level_data::*;
+
+    /// Announces the level the game starts on.
+    pub fn announce_level(level: Res<Level>, mut level_loaded: ResMut<Events<EventLevelLoaded>>) {
+        level_loaded.send(EventLevelLoaded {
+            name: level.name.clone(),
+            obstacles: level.obstacles.len(),
+        });
+    }
+}
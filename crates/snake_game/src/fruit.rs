@@ -3,20 +3,27 @@ pub mod fruit_logic {
     use bevy::prelude::*;
     use crate::snake::snake_data::*;
     use crate::game::game_data::*;
+    use crate::state::state_data::*;
+    use crate::level::level_data::*;
     use rand::*;
     pub fn fruit_spawner(
         mut commands: Commands,
-        mut materials: ResMut<Assets<ColorMaterial>>,
         game: Res<GameState>,
+        level: Res<Level>,
+        mut rng: ResMut<GameRng>,
+        flow: Res<GameFlow>,
         fruit_query: Query<(Entity, &Fruit)>,
         snake_query: Query<&Snake>,
         tail_query: Query<&Tail>
     ){
+        if !flow.is(AppState::Playing) || fruit_query.iter().len() > 0 {
+            return;
+        }
         let cell_size = game.cell_size as f32;
-        let mut rng = rand::thread_rng();    
         let mut rand_x: f32 = 0.0;
         let mut rand_y: f32 = 0.0;
         let max = (game.play_area / cell_size).round() - 2.0;
+        let offset = game.play_area / 2.0 / cell_size - 1.0;
     
         for snake in snake_query.iter() {
             let mut known_positions: Vec<Vec2> = Vec::new();
@@ -25,18 +32,21 @@ pub mod fruit_logic {
             for segment in tail_query.iter() {
                 known_positions.push(segment.position);
             }
+            for obstacle in level.cells() {
+                known_positions.push(obstacle);
+            }
     
             let mut overlaps = true;
     
             while overlaps {
-                let rng_x: f32 = rng.gen();
-                let rng_y: f32 = rng.gen();
-                rand_x = (rng_x * max).round() * cell_size - (game.play_area / 2.0 - cell_size);
-                rand_y = (rng_y * max).round() * cell_size - (game.play_area / 2.0 - cell_size);
+                let rng_x: f32 = rng.rng.gen();
+                let rng_y: f32 = rng.rng.gen();
+                rand_x = (rng_x * max).round() - offset;
+                rand_y = (rng_y * max).round() - offset;
                 let mut found_overlap = false;
     
                 for pos in known_positions.iter() {
-                    if rand_x == pos.x() && rand_y == pos.y(){
+                    if rand_x == pos.x() && rand_y == pos.y() {
                         found_overlap = true;
                         break;
                     }
@@ -48,16 +58,10 @@ pub mod fruit_logic {
             }
         }
     
-        if fruit_query.iter().len() == 0 && game.playing{
-            commands
-                .spawn(SpriteComponents {
-                    material: materials.add(Color::rgb(1.0, 0.0, 0.0).into()),
-                    transform: Transform::from_translation(Vec3::new(rand_x, rand_y, 0.0)),
-                    sprite: Sprite::new(Vec2::new(20.0, 20.0)),
-                    ..Default::default()
-                })
-                .with(Fruit {})
-                .with(Collider::Fruit);
-        }
+        commands.spawn((
+            Fruit { kind: FruitKind::Apple, position: Vec2::new(rand_x, rand_y) },
+            Collider::Fruit,
+            RoundEntity,
+        ));
     }
-}
\ No newline at end of file
+}
@@ -1,104 +1,281 @@
 pub mod game_data {
     use bevy::prelude::*;
+    use rand::rngs::StdRng;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use crate::snake::snake_data::*;
     #[derive(Default)]
     pub struct GameState{
-        // difficulty: f64, Todo with ui
         pub score: usize,
-        pub playing: bool,
         pub play_area: f32,
         pub cell_size: f64,
-        pub prev_scores: Vec<usize>,
+        /// Seconds since the current round started.
+        pub elapsed: f32,
+        pub mode: GameMode,
+        pub difficulty: Difficulty,
+    }
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum GameMode {
+        /// Hitting a wall ends the game.
+        Classic,
+        /// Leaving the board comes back in on the opposite side.
+        Wrap,
+    }
+    impl Default for GameMode {
+        fn default() -> Self {
+            GameMode::Classic
+        }
+    }
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash, Serialize, Deserialize)]
+    pub enum Difficulty {
+        Easy,
+        Normal,
+        Hard,
+    }
+    impl Default for Difficulty {
+        fn default() -> Self {
+            Difficulty::Normal
+        }
+    }
+    impl Difficulty {
+        /// Seconds between snake moves.
+        pub fn tick_seconds(self) -> f32 {
+            match self {
+                Difficulty::Easy => 0.35,
+                Difficulty::Normal => 0.25,
+                Difficulty::Hard => 0.15,
+            }
+        }
     }
     pub struct GameTimer(pub Timer);
-    pub struct EventGameOver;
+    /// Resource: random numbers for fruit placement. Re-seeded at the start
+    /// of every round, with `fixed_seed` if one was given or a fresh seed
+    /// otherwise, so a round can be reproduced from its `seed`.
+    pub struct GameRng {
+        pub fixed_seed: Option<u64>,
+        pub seed: u64,
+        pub rng: StdRng,
+    }
+    /// Why a snake died. The game reports `Wall`, `OwnTail` and, on levels
+    /// with obstacles, `Obstacle`; the rest are left for modes that add
+    /// those hazards.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum DeathCause {
+        Wall,
+        OwnTail,
+        OtherSnake,
+        Obstacle,
+        Poison,
+        Timeout,
+    }
+    /// Sent when a snake dies, describing how the round ended.
+    pub struct EventGameOver {
+        pub snake: Entity,
+        pub cause: DeathCause,
+        /// Grid cell the snake's head was in when it died.
+        pub impact: Vec2,
+        pub score: usize,
+        /// Snake length, head included.
+        pub length: usize,
+        /// Moves made since the round started.
+        pub tick: u32,
+    }
+    /// Resource: what happened during the current round.
+    #[derive(Default)]
+    pub struct RoundStats {
+        pub fruit_eaten: HashMap<FruitKind, usize>,
+        /// Moves made so far.
+        pub ticks: u32,
+    }
+    /// Resource: how the last round ended, shown on the game over screen.
+    #[derive(Default)]
+    pub struct LastRound(pub Option<GameOverSummary>);
+    pub struct GameOverSummary {
+        pub score: usize,
+        pub length: usize,
+        /// Seconds survived.
+        pub duration: f32,
+        pub fruit_eaten: HashMap<FruitKind, usize>,
+        pub cause: DeathCause,
+        /// True if the score beat the best on this mode and difficulty's
+        /// leaderboard.
+        pub record: bool,
+    }
     pub struct EventRestart;
-    pub struct EventUpdateScores;
     pub struct ScoreText;
     pub struct PrevScoreText;
+    pub struct InitialsText;
+    pub struct LeaderboardText;
+    pub struct ScreenText;
+    /// Marks an entity that belongs to the current round (the snake, its
+    /// tail and fruit) so it is despawned when the round ends. Cameras,
+    /// walls and UI are created once at startup and never tagged.
+    pub struct RoundEntity;
 }
 
 pub mod game_functions {
     use bevy::prelude::*;
     use super::game_data::*;
     use crate::snake::snake_data::*;
+    use crate::scores::scores_data::*;
+    use crate::state::state_data::*;
+    use crate::events::events_data::*;
+    use std::collections::VecDeque;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+
+    impl GameRng {
+        pub fn new(fixed_seed: Option<u64>) -> GameRng {
+            let seed = fixed_seed.unwrap_or(0);
+            GameRng { fixed_seed, seed, rng: StdRng::seed_from_u64(seed) }
+        }
+
+        pub fn start_round(&mut self) {
+            self.seed = self.fixed_seed.unwrap_or_else(|| rand::thread_rng().gen());
+            self.rng = StdRng::seed_from_u64(self.seed);
+        }
+    }
+
+    impl RoundStats {
+        pub fn eat(&mut self, kind: FruitKind) {
+            *self.fruit_eaten.entry(kind).or_insert(0) += 1;
+        }
+    }
+
+    impl DeathCause {
+        pub fn describe(self) -> &'static str {
+            match self {
+                DeathCause::Wall => "Hit the wall",
+                DeathCause::OwnTail => "Bit your own tail",
+                DeathCause::OtherSnake => "Ran into another snake",
+                DeathCause::Obstacle => "Hit an obstacle",
+                DeathCause::Poison => "Ate poison",
+                DeathCause::Timeout => "Ran out of time",
+            }
+        }
+    }
 
     pub fn game_over (
         mut commands: Commands,
         mut game_over_reader: Local<EventReader<EventGameOver>>,
         game_over_event: Res<Events<EventGameOver>>,
         mut game: ResMut<GameState>,
-        mut restart: ResMut<Events<EventRestart>>,
-        snake_query: Query<(Entity, &Snake)>,
-        tail_query: Query<(Entity, &Tail)>,
-        fruit_query: Query<(Entity, &Fruit)>
+        stats: Res<RoundStats>,
+        mut last_round: ResMut<LastRound>,
+        mut round_ended: ResMut<Events<EventRoundEnded>>,
+        mut score_changed: ResMut<Events<EventScoreChanged>>,
+        high_scores: Res<HighScores>,
+        mut flow: ResMut<GameFlow>,
+        round_query: Query<(Entity, &RoundEntity)>
     ) {
-        for _ in game_over_reader.iter(&game_over_event) {
+        for event in game_over_reader.iter(&game_over_event) {
             println!("GAME OVER");
-            let mut new_scores: Vec<usize> = Vec::new();
-            let mut bump_down: usize = 9999999999999;
-            let score = &game.score;
-            for scores in game.prev_scores.iter() {
-                if scores > score {
-                    new_scores.push(*scores);
-                } else if scores <= score && bump_down == 9999999999999 {
-                    bump_down = *scores;
-                    new_scores.push(game.score);
-                } else if *score != 0{
-                    new_scores.push(bump_down);
-                    bump_down = *scores;
-                } else {
-                    new_scores.push(0);
-                }
+            let leaderboard = high_scores.leaderboard(game.mode, game.difficulty);
+            let best = leaderboard.entries.first().map(|entry| entry.score).unwrap_or(0);
+            last_round.0 = Some(GameOverSummary {
+                score: event.score,
+                length: event.length,
+                duration: game.elapsed,
+                fruit_eaten: stats.fruit_eaten.clone(),
+                cause: event.cause,
+                record: event.score > best,
+            });
+            round_ended.send(EventRoundEnded {
+                score: event.score,
+                length: event.length,
+                duration: game.elapsed,
+                fruit_eaten: stats.fruit_eaten.clone(),
+                cause: event.cause,
+                record: event.score > best,
+            });
+            if game.score != 0 {
+                score_changed.send(EventScoreChanged { previous: game.score, score: 0 });
             }
-
-            game.prev_scores = new_scores;
             game.score = 0;
-            game.playing = false;
-            for (snake_entity, _) in snake_query.iter() {
-                commands.despawn_recursive(snake_entity);
-            }
-            for (tail_entity, _) in tail_query.iter() {
-                commands.despawn_recursive(tail_entity);
-            }
-            for (fruit_entity, _) in fruit_query.iter() {
-                commands.despawn_recursive(fruit_entity);
+            flow.set(AppState::GameOver);
+            despawn_round(&mut commands, &round_query);
+        }
+    }
+
+    /// Removes every entity tagged with `RoundEntity`.
+    fn despawn_round(commands: &mut Commands, round_query: &Query<(Entity, &RoundEntity)>) {
+        for (entity, _) in round_query.iter() {
+            commands.despawn_recursive(entity);
+        }
+    }
+
+    /// Clears away an unfinished round when the player quits to the title
+    /// screen from the pause menu.
+    pub fn quit_round (
+        mut commands: Commands,
+        mut state_reader: Local<EventReader<EventStateChange>>,
+        state_events: Res<Events<EventStateChange>>,
+        round_query: Query<(Entity, &RoundEntity)>
+    ) {
+        for change in state_reader.iter(&state_events) {
+            if change.entered(AppState::Title) {
+                despawn_round(&mut commands, &round_query);
             }
-            restart.send(EventRestart {});
         }
-    } 
+    }
+
+    /// Counts how long the current round has been running.
+    pub fn round_clock(time: Res<Time>, flow: Res<GameFlow>, mut game: ResMut<GameState>) {
+        if flow.is(AppState::Playing) {
+            game.elapsed += time.delta_seconds;
+        }
+    }
     
+    /// Spawns the snake for a new round.
+    pub fn spawn_round(commands: &mut Commands) {
+        let snake_pos = Vec2::new(0.0, -6.0);
+        let last_pos = Vec2::new(-1.0, -6.0);
+        commands.spawn((
+            Snake {
+                player: 0,
+                direction: SnakeDirection::RIGHT,
+                last_direction: SnakeDirection::RIGHT,
+                position: snake_pos,
+                last_position: last_pos,
+                moves: VecDeque::new(),
+            },
+            Collider::Snake,
+            RoundEntity,
+        ));
+    }
+
+    /// Starts a new round: clears whatever is left of the last one, resets
+    /// the per-round state and spawns a fresh snake.
     pub fn restart (
         mut commands: Commands,
         mut restart_reader: Local<EventReader<EventRestart>>,
         restart_event: Res<Events<EventRestart>>,
         mut game: ResMut<GameState>,
-        mut materials: ResMut<Assets<ColorMaterial>>,
+        mut stats: ResMut<RoundStats>,
+        mut timer: ResMut<GameTimer>,
+        mut rng: ResMut<GameRng>,
+        mut flow: ResMut<GameFlow>,
+        mut round_started: ResMut<Events<EventRoundStarted>>,
+        mut score_changed: ResMut<Events<EventScoreChanged>>,
+        round_query: Query<(Entity, &RoundEntity)>
     ) {
-        if !game.playing {
-            for _ in restart_reader.iter(&restart_event) {
-                println!("RESTART");
-                let cell_size = game.cell_size as f32;
-                let snake_pos = Vec2::new(0.0, -6.0);
-                let last_pos = Vec2::new(-1.0, -6.0);
-                commands
-                    .spawn(Camera2dComponents::default())
-                    .spawn(UiCameraComponents::default())
-                    .spawn(SpriteComponents {
-                        material: materials.add(Color::rgb(0.0, 1.0, 0.0).into()),
-                        transform: Transform::from_translation(Vec3::new(0.0, snake_pos.y() * game.cell_size as f32, 0.0)),
-                        sprite: Sprite::new(Vec2::new(cell_size - 2.0, cell_size - 2.0)),
-                        ..Default::default()
-                    })
-                    .with(Snake { 
-                        direction: SnakeDirection::RIGHT,
-                        position: snake_pos,
-                        last_position: last_pos,
-                        movement_locked: false,
-                        next_move: SnakeDirection::RIGHT
-                    })
-                    .with(Collider::Snake);
-                game.playing = true;
+        if restart_reader.iter(&restart_event).next().is_some() && !flow.is(AppState::Playing) {
+            println!("RESTART");
+            despawn_round(&mut commands, &round_query);
+            spawn_round(&mut commands);
+            if game.score != 0 {
+                score_changed.send(EventScoreChanged { previous: game.score, score: 0 });
             }
+            game.score = 0;
+            game.elapsed = 0.0;
+            *stats = RoundStats::default();
+            timer.0.reset();
+            rng.start_round();
+            round_started.send(EventRoundStarted {
+                seed: rng.seed,
+                mode: game.mode,
+                difficulty: game.difficulty,
+            });
+            flow.set(AppState::Playing);
         }
     }
-}
\ No newline at end of file
+}
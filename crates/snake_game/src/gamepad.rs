@@ -0,0 +1,182 @@
+pub mod gamepad_data {
+    use bevy::prelude::*;
+    use serde::{Deserialize, Serialize};
+    use std::collections::HashMap;
+    use crate::snake::snake_data::*;
+
+    /// Pauses and resumes the game.
+    pub const PAUSE_BUTTON: GamepadButtonType = GamepadButtonType::Start;
+    /// Starts a game from the title screen and retries after a game over.
+    pub const RESTART_BUTTON: GamepadButtonType = GamepadButtonType::South;
+
+    /// Gamepad settings from the config file.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct GamepadConfig {
+        /// How far the left stick must be pushed, between 0 and 1, before
+        /// it steers.
+        pub deadzone: f32,
+    }
+
+    /// Resource: which gamepad each player uses. Pads are handed out in the
+    /// order they connect, and a player whose pad disconnects keeps the
+    /// slot for the next pad to connect.
+    #[derive(Default)]
+    pub struct GamepadAssignments {
+        pub players: Vec<Option<Gamepad>>,
+    }
+
+    /// Direction each gamepad's stick pointed on the last frame, so a held
+    /// stick only queues one turn.
+    #[derive(Default)]
+    pub struct StickState {
+        pub last: HashMap<Gamepad, Option<SnakeDirection>>,
+    }
+}
+
+pub mod gamepad_functions {
+    use bevy::prelude::*;
+    use super::gamepad_data::*;
+    use crate::config::config_data::*;
+    use crate::snake::snake_data::*;
+    use crate::state::state_data::*;
+    use crate::bindings::bindings_data::*;
+
+    impl Default for GamepadConfig {
+        fn default() -> Self {
+            GamepadConfig { deadzone: 0.5 }
+        }
+    }
+
+    impl GamepadAssignments {
+        pub fn connect(&mut self, gamepad: Gamepad) {
+            if self.players.contains(&Some(gamepad)) {
+                return;
+            }
+            match self.players.iter_mut().find(|slot| slot.is_none()) {
+                Some(slot) => *slot = Some(gamepad),
+                None => self.players.push(Some(gamepad)),
+            }
+        }
+
+        pub fn disconnect(&mut self, gamepad: Gamepad) {
+            for slot in self.players.iter_mut() {
+                if *slot == Some(gamepad) {
+                    *slot = None;
+                }
+            }
+        }
+
+        /// The gamepad assigned to `player`, counting from 0.
+        pub fn gamepad(&self, player: usize) -> Option<Gamepad> {
+            self.players.get(player).copied().flatten()
+        }
+
+        /// True if `button` was just pressed on any assigned gamepad.
+        pub fn just_pressed(&self, buttons: &Input<GamepadButton>, button: GamepadButtonType) -> bool {
+            self.players
+                .iter()
+                .flatten()
+                .any(|gamepad| buttons.just_pressed(GamepadButton(*gamepad, button)))
+        }
+    }
+
+    /// Direction the stick points along its dominant axis, or `None` inside
+    /// the deadzone.
+    pub fn stick_direction(x: f32, y: f32, deadzone: f32) -> Option<SnakeDirection> {
+        if x.abs() < deadzone && y.abs() < deadzone {
+            None
+        } else if x.abs() > y.abs() {
+            Some(if x > 0.0 { SnakeDirection::RIGHT } else { SnakeDirection::LEFT })
+        } else {
+            Some(if y > 0.0 { SnakeDirection::UP } else { SnakeDirection::DOWN })
+        }
+    }
+
+    pub fn track_gamepads(
+        mut gamepad_reader: Local<EventReader<GamepadEvent>>,
+        gamepad_events: Res<Events<GamepadEvent>>,
+        mut assignments: ResMut<GamepadAssignments>,
+    ) {
+        for GamepadEvent(gamepad, event_type) in gamepad_reader.iter(&gamepad_events) {
+            match event_type {
+                GamepadEventType::Connected => assignments.connect(*gamepad),
+                GamepadEventType::Disconnected => assignments.disconnect(*gamepad),
+            }
+        }
+    }
+
+    /// Queues turns from each player's D-pad and left stick, the same way
+    /// `snake_movement` queues them from the keyboard. With relative
+    /// steering the D-pad's left and right turn the snake and the stick is
+    /// not used.
+    pub fn gamepad_steering(
+        buttons: Res<Input<GamepadButton>>,
+        axes: Res<Axis<GamepadAxis>>,
+        assignments: Res<GamepadAssignments>,
+        config: Res<GameConfig>,
+        bindings: Res<KeyBindings>,
+        flow: Res<GameFlow>,
+        mut sticks: Local<StickState>,
+        mut query: Query<&mut Snake>,
+    ) {
+        if !flow.is(AppState::Playing) {
+            return;
+        }
+        let dpad = [
+            (GamepadButtonType::DPadUp, SnakeDirection::UP),
+            (GamepadButtonType::DPadDown, SnakeDirection::DOWN),
+            (GamepadButtonType::DPadLeft, SnakeDirection::LEFT),
+            (GamepadButtonType::DPadRight, SnakeDirection::RIGHT),
+        ];
+        for mut snake in query.iter_mut() {
+            let gamepad = match assignments.gamepad(snake.player) {
+                Some(gamepad) => gamepad,
+                None => continue,
+            };
+            if !bindings.steering.uses_direction_keys() {
+                if buttons.just_pressed(GamepadButton(gamepad, GamepadButtonType::DPadLeft)) {
+                    snake.queue_turn(false);
+                } else if buttons.just_pressed(GamepadButton(gamepad, GamepadButtonType::DPadRight)) {
+                    snake.queue_turn(true);
+                }
+                continue;
+            }
+            for (button, direction) in dpad.iter() {
+                if buttons.just_pressed(GamepadButton(gamepad, *button)) {
+                    snake.queue_move(*direction);
+                }
+            }
+            let x = axes.get(&GamepadAxis(gamepad, GamepadAxisType::LeftStickX)).unwrap_or(0.0);
+            let y = axes.get(&GamepadAxis(gamepad, GamepadAxisType::LeftStickY)).unwrap_or(0.0);
+            let direction = stick_direction(x, y, config.gamepad.deadzone);
+            let last = sticks.last.insert(gamepad, direction).flatten();
+            if let Some(direction) = direction {
+                if last != Some(direction) {
+                    snake.queue_move(direction);
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn the_deadzone_gives_no_direction() {
+            assert_eq!(stick_direction(0.0, 0.0, 0.3), None);
+            assert_eq!(stick_direction(0.29, -0.29, 0.3), None);
+            assert_eq!(stick_direction(0.3, 0.0, 0.3), Some(SnakeDirection::RIGHT));
+        }
+
+        #[test]
+        fn the_dominant_axis_wins() {
+            assert_eq!(stick_direction(0.9, 0.4, 0.3), Some(SnakeDirection::RIGHT));
+            assert_eq!(stick_direction(-0.9, 0.4, 0.3), Some(SnakeDirection::LEFT));
+            assert_eq!(stick_direction(0.2, 0.8, 0.3), Some(SnakeDirection::UP));
+            assert_eq!(stick_direction(0.2, -0.8, 0.3), Some(SnakeDirection::DOWN));
+            assert_eq!(stick_direction(0.7, -0.7, 0.3), Some(SnakeDirection::DOWN));
+        }
+    }
+}
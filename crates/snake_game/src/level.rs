@@ -0,0 +1,55 @@
+pub mod level_data {
+    use serde::{Deserialize, Serialize};
+
+    /// Resource: the board layout. The default level is an empty board.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Level {
+        pub name: String,
+        /// Grid cells `(x, y)` blocked by obstacles, with `(0, 0)` at the
+        /// centre of the board.
+        pub obstacles: Vec<(i32, i32)>,
+    }
+}
+
+pub mod level_functions {
+    use bevy::prelude::*;
+    use std::fs;
+    use std::path::Path;
+    use super::level_data::*;
+
+    /// Cells the snake starts on or runs into during its first few moves,
+    /// which must stay clear of obstacles.
+    const SPAWN_CELLS: [(i32, i32); 5] = [(-1, -6), (0, -6), (1, -6), (2, -6), (3, -6)];
+
+    impl Level {
+        /// Reads the level at `path`.
+        pub fn load(path: &Path) -> Result<Level, String> {
+            let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+            ron::de::from_str(&text).map_err(|err| format!("invalid level `{}`: {}", path.display(), err))
+        }
+
+        /// Checks that every obstacle is inside a board reaching `grid_max`
+        /// cells from the centre and none is in the snake's way at the start.
+        pub fn validate(&self, grid_max: f32) -> Result<(), String> {
+            let inside = grid_max as i32 - 1;
+            for (x, y) in self.obstacles.iter() {
+                if x.abs() > inside || y.abs() > inside {
+                    return Err(format!("obstacle ({}, {}) is outside the board", x, y));
+                }
+                if SPAWN_CELLS.contains(&(*x, *y)) {
+                    return Err(format!("obstacle ({}, {}) blocks the snake's starting position", x, y));
+                }
+            }
+            Ok(())
+        }
+
+        pub fn blocks(&self, position: Vec2) -> bool {
+            self.obstacles.contains(&(position.x() as i32, position.y() as i32))
+        }
+
+        pub fn cells(&self) -> Vec<Vec2> {
+            self.obstacles.iter().map(|(x, y)| Vec2::new(*x as f32, *y as f32)).collect()
+        }
+    }
+}
@@ -10,3 +10,22 @@ pub mod snake;
 pub mod fruit;
 pub mod game;
 pub mod ui;
+pub mod render;
+pub mod config;
+pub mod level;
+pub mod replay;
+pub mod sim;
+pub mod controller;
+pub mod tournament;
+pub mod training;
+pub mod dataset;
+pub mod scores;
+pub mod state;
+pub mod events;
+pub mod bindings;
+pub mod gamepad;
+pub mod steering;
+pub mod pointer;
+pub mod theme;
+pub mod effects;
+pub mod audio;
@@ -0,0 +1,148 @@
+pub mod pointer_data {
+    use bevy::prelude::*;
+
+    /// Pixels a finger must travel before a touch counts as a swipe.
+    pub const SWIPE_MIN_PIXELS: f32 = 30.0;
+
+    /// Where the mouse is and where the current swipe started, in window
+    /// pixels with `y` pointing up.
+    #[derive(Default)]
+    pub struct PointerState {
+        pub cursor: Option<Vec2>,
+        pub swipe_start: Option<Vec2>,
+    }
+}
+
+pub mod pointer_functions {
+    use bevy::prelude::*;
+    use bevy::input::touch::{TouchInput, TouchPhase};
+    use bevy::window::{CursorMoved, Windows};
+    use super::pointer_data::*;
+    use crate::bindings::bindings_data::*;
+    use crate::game::game_data::*;
+    use crate::snake::snake_data::*;
+    use crate::state::state_data::*;
+    use crate::steering::steering_data::*;
+
+    /// Direction along the dominant axis of `delta`, or `None` for no
+    /// movement.
+    pub fn dominant_direction(delta: Vec2) -> Option<SnakeDirection> {
+        if delta.x() == 0.0 && delta.y() == 0.0 {
+            None
+        } else if delta.x().abs() > delta.y().abs() {
+            Some(if delta.x() > 0.0 { SnakeDirection::RIGHT } else { SnakeDirection::LEFT })
+        } else {
+            Some(if delta.y() > 0.0 { SnakeDirection::UP } else { SnakeDirection::DOWN })
+        }
+    }
+
+    /// Direction that takes a snake heading `heading` towards a point
+    /// `delta` away. A point behind the snake is reached by turning along
+    /// the other axis first.
+    pub fn direction_towards(delta: Vec2, heading: SnakeDirection) -> Option<SnakeDirection> {
+        let direction = dominant_direction(delta)?;
+        if direction != heading.opposite() {
+            return Some(direction);
+        }
+        match heading {
+            SnakeDirection::LEFT | SnakeDirection::RIGHT => dominant_direction(Vec2::new(0.0, delta.y())),
+            SnakeDirection::UP | SnakeDirection::DOWN => dominant_direction(Vec2::new(delta.x(), 0.0)),
+        }
+    }
+
+    /// In `SteeringMode::Pointer`, holding the left mouse button steers the
+    /// head towards the cursor and each swipe on a touchscreen turns the
+    /// snake the way the finger moved. Screen positions are mapped to the
+    /// board through the camera made by `spawn_cameras`, centred on the
+    /// board at a scale of one pixel per unit.
+    pub fn pointer_steering(
+        mouse_buttons: Res<Input<MouseButton>>,
+        mut cursor_reader: Local<EventReader<CursorMoved>>,
+        cursor_events: Res<Events<CursorMoved>>,
+        mut touch_reader: Local<EventReader<TouchInput>>,
+        touch_events: Res<Events<TouchInput>>,
+        windows: Res<Windows>,
+        game: Res<GameState>,
+        bindings: Res<KeyBindings>,
+        flow: Res<GameFlow>,
+        mut pointer: Local<PointerState>,
+        mut query: Query<&mut Snake>,
+    ) {
+        for event in cursor_reader.iter(&cursor_events) {
+            pointer.cursor = Some(event.position);
+        }
+        let mut swipes = Vec::new();
+        for touch in touch_reader.iter(&touch_events) {
+            // Touches count `y` down from the top of the window.
+            let position = Vec2::new(touch.position.x(), -touch.position.y());
+            match touch.phase {
+                TouchPhase::Started => pointer.swipe_start = Some(position),
+                TouchPhase::Moved | TouchPhase::Ended => {
+                    if let Some(start) = pointer.swipe_start {
+                        let delta = position - start;
+                        if delta.length() >= SWIPE_MIN_PIXELS {
+                            swipes.extend(dominant_direction(delta));
+                            pointer.swipe_start = Some(position);
+                        }
+                    }
+                    if touch.phase == TouchPhase::Ended {
+                        pointer.swipe_start = None;
+                    }
+                }
+                TouchPhase::Cancelled => pointer.swipe_start = None,
+            }
+        }
+        if !flow.is(AppState::Playing) || bindings.steering != SteeringMode::Pointer {
+            return;
+        }
+        let target = match (pointer.cursor, windows.get_primary()) {
+            (Some(cursor), Some(window)) if mouse_buttons.pressed(MouseButton::Left) => {
+                Some(cursor - Vec2::new(window.width() as f32 / 2.0, window.height() as f32 / 2.0))
+            }
+            _ => None,
+        };
+        let cell_size = game.cell_size as f32;
+        for mut snake in query.iter_mut() {
+            for direction in swipes.iter() {
+                snake.queue_move(*direction);
+            }
+            if let Some(target) = target {
+                let delta = target - snake.position * cell_size;
+                if delta.length() < cell_size / 2.0 {
+                    continue;
+                }
+                let heading = *snake.moves.back().unwrap_or(&snake.direction);
+                if let Some(direction) = direction_towards(delta, heading) {
+                    snake.queue_move(direction);
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn a_point_ahead_or_to_the_side_is_headed_for_directly() {
+            let heading = SnakeDirection::UP;
+            assert_eq!(direction_towards(Vec2::new(0.0, 0.0), heading), None);
+            assert_eq!(direction_towards(Vec2::new(1.0, 5.0), heading), Some(SnakeDirection::UP));
+            assert_eq!(direction_towards(Vec2::new(-5.0, 1.0), heading), Some(SnakeDirection::LEFT));
+            assert_eq!(direction_towards(Vec2::new(5.0, -1.0), heading), Some(SnakeDirection::RIGHT));
+        }
+
+        #[test]
+        fn a_point_behind_turns_along_the_other_axis() {
+            assert_eq!(
+                direction_towards(Vec2::new(2.0, -5.0), SnakeDirection::UP),
+                Some(SnakeDirection::RIGHT)
+            );
+            assert_eq!(
+                direction_towards(Vec2::new(-5.0, 2.0), SnakeDirection::RIGHT),
+                Some(SnakeDirection::UP)
+            );
+            assert_eq!(direction_towards(Vec2::new(-5.0, 0.0), SnakeDirection::RIGHT), None);
+        }
+    }
+}
@@ -0,0 +1,458 @@
+pub mod game_render {
+    use bevy::prelude::*;
+    use bevy::ecs::Component;
+    use std::collections::{HashMap, HashSet};
+    use std::f32::consts::PI;
+    use crate::snake::snake_data::*;
+    use crate::snake::snake_functions::*;
+    use crate::game::game_data::*;
+    use crate::config::config_data::*;
+    use crate::config::config_functions::*;
+    use crate::level::level_data::*;
+    use crate::theme::theme_data::*;
+    use bevy::render::pass::ClearColor;
+    use bevy::render::texture::TextureFormat;
+
+    /// A theme's sprite atlas is one row of square tiles, drawn for a snake
+    /// heading right.
+    pub const ATLAS_TILE: f32 = 32.0;
+    pub const ATLAS_TILES: usize = 5;
+
+    /// Head, eyes to the right.
+    pub const TILE_HEAD: u32 = 0;
+    /// Body running left to right.
+    pub const TILE_STRAIGHT: u32 = 1;
+    /// Body joining the cells to the right and above.
+    pub const TILE_CORNER: u32 = 2;
+    /// Last tail segment, joined to the cell on its right.
+    pub const TILE_TIP: u32 = 3;
+    pub const TILE_FRUIT: u32 = 4;
+
+    /// How much of its color every other tail segment keeps when the tail
+    /// is patterned.
+    pub const TAIL_PATTERN_SHADE: f32 = 0.55;
+
+    /// Depth of the theme's background image, just in front of the
+    /// camera's far plane so the board is drawn over it.
+    pub const BACKGROUND_Z: f32 = -0.05;
+
+    /// Resource: what the current theme has loaded.
+    #[derive(Default)]
+    pub struct ThemeAssets {
+        /// Shared by the walls and obstacles, recolored on a theme change.
+        pub wall: Handle<ColorMaterial>,
+        /// Empty when the theme draws flat squares.
+        pub atlas: Option<Handle<TextureAtlas>>,
+        /// A single white pixel, tinted and stretched into the flat squares
+        /// drawn when the theme has no atlas.
+        pub blank: Handle<TextureAtlas>,
+        pub background: Option<Entity>,
+    }
+
+    /// Anything drawn on one grid cell of the board.
+    pub trait GridCell {
+        fn cell(&self) -> Vec2;
+    }
+
+    impl GridCell for Snake {
+        fn cell(&self) -> Vec2 {
+            self.position
+        }
+    }
+
+    impl GridCell for Tail {
+        fn cell(&self) -> Vec2 {
+            self.position
+        }
+    }
+
+    impl GridCell for Fruit {
+        fn cell(&self) -> Vec2 {
+            self.position
+        }
+    }
+
+    /// Marks the camera that looks at the board, which screen shake moves.
+    pub struct BoardCamera;
+
+    /// The cells a sprite is gliding between. The sprite reaches `to` as the
+    /// game timer reaches its next tick, so it trails the grid by one move.
+    pub struct Glide {
+        pub from: Vec2,
+        pub to: Vec2,
+    }
+
+    // Cameras, walls and obstacles last the whole session. Each round's
+    // snake and fruit are spawned by `restart`.
+    pub fn spawn_cameras(mut commands: Commands) {
+        commands
+            .spawn(Camera2dComponents::default())
+            .with(BoardCamera)
+            .spawn(UiCameraComponents::default());
+    }
+
+    pub fn spawn_walls(
+        mut commands: Commands,
+        game: Res<GameState>,
+        theme_assets: Res<ThemeAssets>,
+    ){
+            let cell_size = game.cell_size as f32;
+            let wall_material = theme_assets.wall.clone();
+            let wall_thickness = cell_size;
+            let bounds = Vec2::new(game.play_area, game.play_area);
+
+        commands
+            // left
+            .spawn(SpriteComponents {
+                material: wall_material.clone(),
+                transform: Transform::from_translation(Vec3::new(-bounds.x() / 2.0, 0.0, 0.0)),
+                sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
+                ..Default::default()
+            })
+            .with(Collider::Solid)
+            // right
+            .spawn(SpriteComponents {
+                material: wall_material.clone(),
+                transform: Transform::from_translation(Vec3::new(bounds.x() / 2.0, 0.0, 0.0)),
+                sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
+                ..Default::default()
+            })
+            .with(Collider::Solid)
+            // bottom
+            .spawn(SpriteComponents {
+                material: wall_material.clone(),
+                transform: Transform::from_translation(Vec3::new(0.0, -bounds.y() / 2.0, 0.0)),
+                sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
+                ..Default::default()
+            })
+            .with(Collider::Solid)
+            // top
+            .spawn(SpriteComponents {
+                material: wall_material.clone(),
+                transform: Transform::from_translation(Vec3::new(0.0, bounds.y() / 2.0, 0.0)),
+                sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
+                ..Default::default()
+            })
+            .with(Collider::Solid);
+        println!("SNAKE!");
+    }
+
+    pub fn spawn_obstacles(
+        mut commands: Commands,
+        game: Res<GameState>,
+        level: Res<Level>,
+        theme_assets: Res<ThemeAssets>,
+    ){
+        let cell_size = game.cell_size as f32;
+        let wall_material = theme_assets.wall.clone();
+        for obstacle in level.cells() {
+            commands
+                .spawn(SpriteComponents {
+                    material: wall_material.clone(),
+                    transform: Transform::from_translation(Vec3::new(
+                        obstacle.x() * cell_size,
+                        obstacle.y() * cell_size,
+                        0.0
+                    )),
+                    sprite: Sprite::new(Vec2::new(cell_size, cell_size)),
+                    ..Default::default()
+                })
+                .with(Collider::Solid);
+        }
+    }
+
+    /// Loads the theme the game starts with and the blank square before
+    /// anything is spawned.
+    pub fn load_theme(
+        mut commands: Commands,
+        game: Res<GameState>,
+        config: Res<GameConfig>,
+        themes: Res<Themes>,
+        asset_server: Res<AssetServer>,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+        mut textures: ResMut<Assets<Texture>>,
+        mut atlases: ResMut<Assets<TextureAtlas>>,
+        mut clear_color: ResMut<ClearColor>,
+        mut theme_assets: ResMut<ThemeAssets>,
+    ) {
+        let theme = themes.look(config.display.color_vision);
+        clear_color.0 = rgb(theme.palette.background);
+        use_theme(
+            &theme,
+            &mut commands,
+            &game,
+            &asset_server,
+            &mut materials,
+            &mut atlases,
+            &mut theme_assets,
+        );
+        let blank = textures.add(Texture::new_fill(Vec2::new(1.0, 1.0), &[255, 255, 255, 255], TextureFormat::Rgba8UnormSrgb));
+        theme_assets.blank = atlases.add(TextureAtlas::from_grid(blank, Vec2::new(1.0, 1.0), 1, 1));
+    }
+
+    /// Switches the background, walls and atlas over when the settings
+    /// screen picks another theme or palette. `attach_sprites` redraws the
+    /// snake and fruit.
+    pub fn apply_theme(
+        mut commands: Commands,
+        mut theme_reader: Local<EventReader<EventThemeChanged>>,
+        theme_events: Res<Events<EventThemeChanged>>,
+        game: Res<GameState>,
+        config: Res<GameConfig>,
+        themes: Res<Themes>,
+        asset_server: Res<AssetServer>,
+        mut materials: ResMut<Assets<ColorMaterial>>,
+        mut atlases: ResMut<Assets<TextureAtlas>>,
+        mut clear_color: ResMut<ClearColor>,
+        mut theme_assets: ResMut<ThemeAssets>,
+    ) {
+        if theme_reader.iter(&theme_events).last().is_none() {
+            return;
+        }
+        let theme = themes.look(config.display.color_vision);
+        clear_color.0 = rgb(theme.palette.background);
+        use_theme(
+            &theme,
+            &mut commands,
+            &game,
+            &asset_server,
+            &mut materials,
+            &mut atlases,
+            &mut theme_assets,
+        );
+    }
+
+    fn use_theme(
+        theme: &Theme,
+        commands: &mut Commands,
+        game: &GameState,
+        asset_server: &AssetServer,
+        materials: &mut Assets<ColorMaterial>,
+        atlases: &mut Assets<TextureAtlas>,
+        theme_assets: &mut ThemeAssets,
+    ) {
+        match materials.get_mut(&theme_assets.wall) {
+            Some(wall) => wall.color = rgb(theme.palette.wall),
+            None => theme_assets.wall = materials.add(rgb(theme.palette.wall).into()),
+        }
+        theme_assets.atlas = theme.atlas.as_ref().map(|path| {
+            let texture = asset_server.load(path.as_str());
+            atlases.add(TextureAtlas::from_grid(texture, Vec2::new(ATLAS_TILE, ATLAS_TILE), ATLAS_TILES, 1))
+        });
+        if let Some(background) = theme_assets.background.take() {
+            commands.despawn(background);
+        }
+        if let Some(path) = &theme.background {
+            let texture: Handle<Texture> = asset_server.load(path.as_str());
+            commands.spawn(SpriteComponents {
+                material: materials.add(texture.into()),
+                transform: Transform::from_translation(Vec3::new(0.0, 0.0, BACKGROUND_Z)),
+                sprite: Sprite::new(Vec2::new(game.play_area, game.play_area)),
+                ..Default::default()
+            });
+            theme_assets.background = commands.current_entity();
+        }
+    }
+
+    /// Gives every snake, tail segment and fruit spawned by the game rules
+    /// a sprite the first time it is seen: an atlas tile if the theme has
+    /// an atlas, a flat square otherwise. After the look changes the atlas,
+    /// tile, color and size are swapped on the sprites already there, so
+    /// pieces keep their place and any glide in progress.
+    pub fn attach_sprites(
+        mut commands: Commands,
+        mut drawn: Local<HashSet<Entity>>,
+        mut theme_reader: Local<EventReader<EventThemeChanged>>,
+        theme_events: Res<Events<EventThemeChanged>>,
+        game: Res<GameState>,
+        config: Res<GameConfig>,
+        themes: Res<Themes>,
+        theme_assets: Res<ThemeAssets>,
+        snake_query: Query<(Entity, &Snake)>,
+        tail_query: Query<(Entity, &Tail)>,
+        fruit_query: Query<(Entity, &Fruit)>,
+        mut sprite_query: Query<(&mut Handle<TextureAtlas>, &mut TextureAtlasSprite, &mut Transform)>,
+    ) {
+        let redraw = theme_reader.iter(&theme_events).last().is_some();
+        let display = &config.display;
+        let palette = themes.look(display.color_vision).palette;
+        let white = [1.0, 1.0, 1.0];
+        let cell_size = game.cell_size as f32;
+        let tile_scale = cell_size / ATLAS_TILE;
+        // An atlas tile tinted white, or the blank square tinted and
+        // stretched to `size`.
+        let look = |tile: u32, color: [f32; 3], size: f32| match &theme_assets.atlas {
+            Some(atlas) => (atlas.clone(), tile, white, tile_scale),
+            None => (theme_assets.blank.clone(), 0, color, size),
+        };
+        let mut alive = HashSet::new();
+        let mut pieces = Vec::new();
+        for (entity, snake) in snake_query.iter() {
+            pieces.push((entity, snake.position, look(TILE_HEAD, palette.snake, cell_size - 2.0)));
+        }
+        for (entity, segment) in tail_query.iter() {
+            let shade = if display.tail_pattern && segment.index % 2 == 1 { TAIL_PATTERN_SHADE } else { 1.0 };
+            let (atlas, tile, tint, scale) = look(TILE_STRAIGHT, palette.tail, cell_size - 2.0);
+            let tint = [tint[0] * shade, tint[1] * shade, tint[2] * shade];
+            pieces.push((entity, segment.position, (atlas, tile, tint, scale)));
+        }
+        for (entity, fruit) in fruit_query.iter() {
+            pieces.push((entity, fruit.position, look(TILE_FRUIT, palette.fruit, cell_size * 0.8)));
+        }
+        for (entity, position, (atlas, tile, tint, scale)) in pieces {
+            alive.insert(entity);
+            if drawn.contains(&entity) {
+                if !redraw {
+                    continue;
+                }
+                if let Ok((mut handle, mut sprite, mut transform)) = sprite_query.get_mut(entity) {
+                    *handle = atlas;
+                    sprite.index = tile;
+                    sprite.color = rgb(tint);
+                    transform.scale = Vec3::new(scale, scale, 1.0);
+                }
+                continue;
+            }
+            drawn.insert(entity);
+            let mut transform = Transform::from_translation(snake_pos_to_translation(position, game.cell_size));
+            transform.scale = Vec3::new(scale, scale, 1.0);
+            commands.insert(entity, SpriteSheetComponents {
+                texture_atlas: atlas,
+                sprite: TextureAtlasSprite {
+                    index: tile,
+                    color: rgb(tint),
+                },
+                transform,
+                ..Default::default()
+            });
+            commands.insert_one(entity, Glide { from: position, to: position });
+        }
+        drawn.retain(|entity| alive.contains(entity));
+    }
+
+    /// Moves the sprite of each `T` towards the grid cell it is on. With
+    /// smooth movement on, sprites slide from their last cell over the
+    /// course of a tick; otherwise, and when a piece wraps to the far side
+    /// of the board, they jump straight there.
+    pub fn sync_transforms<T: GridCell + Component>(
+        game: Res<GameState>,
+        config: Res<GameConfig>,
+        timer: Res<GameTimer>,
+        mut query: Query<(&T, &mut Glide, &mut Transform)>,
+    ) {
+        let progress = if config.display.smooth_movement && timer.0.duration > 0.0 {
+            (timer.0.elapsed / timer.0.duration).min(1.0)
+        } else {
+            1.0
+        };
+        for (piece, mut glide, mut transform) in query.iter_mut() {
+            let cell = piece.cell();
+            if cell != glide.to {
+                glide.from = if (cell - glide.to).length() > 1.0 { cell } else { glide.to };
+                glide.to = cell;
+            }
+            let position = glide.from + (glide.to - glide.from) * progress;
+            transform.translation = snake_pos_to_translation(position, game.cell_size);
+        }
+    }
+
+    /// Picks the atlas tile and rotation for each part of the snake from
+    /// the cells either side of it: the head faces the way the snake is
+    /// going, segments between two cells in a line are straight, others
+    /// are corners, and the last segment is the tip. Flat squares are left
+    /// as they are.
+    pub fn orient_sprites(
+        theme_assets: Res<ThemeAssets>,
+        snake_query: Query<&Snake>,
+        tail_query: Query<(Entity, &Tail)>,
+        mut sprite_query: Query<(Entity, &mut TextureAtlasSprite, &mut Transform)>,
+    ) {
+        if theme_assets.atlas.is_none() {
+            return;
+        }
+        let mut tiles = HashMap::new();
+        for snake in snake_query.iter() {
+            let mut segments: Vec<(Entity, &Tail)> = tail_query.iter().collect();
+            segments.sort_by_key(|(_, tail)| tail.index);
+            let segments: Vec<(Entity, Vec2)> = segments.iter().map(|(entity, tail)| (*entity, tail.position)).collect();
+            let mut ahead = snake.position;
+            for (i, (entity, position)) in segments.iter().enumerate() {
+                let towards_head = neighbour_direction(*position, ahead);
+                let towards_tip = segments.get(i + 1).and_then(|(_, behind)| neighbour_direction(*position, *behind));
+                let tile = match (towards_head, towards_tip) {
+                    (Some(head), Some(tip)) if head == tip.opposite() => (TILE_STRAIGHT, angle(head)),
+                    (Some(head), Some(tip)) => {
+                        if tip == head.turn_left() {
+                            (TILE_CORNER, angle(head))
+                        } else {
+                            (TILE_CORNER, angle(tip))
+                        }
+                    }
+                    (Some(head), None) => (TILE_TIP, angle(head)),
+                    (None, _) => (TILE_STRAIGHT, 0.0),
+                };
+                tiles.insert(*entity, tile);
+                ahead = *position;
+            }
+        }
+        for (entity, mut sprite, mut transform) in sprite_query.iter_mut() {
+            if let Some((tile, rotation)) = tiles.get(&entity) {
+                sprite.index = *tile;
+                transform.rotation = Quat::from_rotation_z(*rotation);
+            }
+        }
+    }
+
+    /// Turns the head to face the way the snake is going.
+    pub fn orient_heads(mut query: Query<(&Snake, &mut Transform)>) {
+        for (snake, mut transform) in query.iter_mut() {
+            transform.rotation = Quat::from_rotation_z(angle(snake.direction));
+        }
+    }
+
+    /// Rotation that turns a tile drawn facing right to face `direction`.
+    fn angle(direction: SnakeDirection) -> f32 {
+        match direction {
+            SnakeDirection::RIGHT => 0.0,
+            SnakeDirection::UP => PI / 2.0,
+            SnakeDirection::LEFT => PI,
+            SnakeDirection::DOWN => PI * 1.5,
+        }
+    }
+
+    /// Direction from `cell` to the next cell along the snake, which may be
+    /// on the far side of the board after a wrap.
+    fn neighbour_direction(cell: Vec2, next: Vec2) -> Option<SnakeDirection> {
+        let step = |delta: f32| if delta.abs() > 1.0 { -delta.signum() } else { delta };
+        let (x, y) = (step(next.x() - cell.x()), step(next.y() - cell.y()));
+        SnakeDirection::ALL
+            .iter()
+            .copied()
+            .find(|direction| direction.step(Vec2::zero()) == Vec2::new(x, y))
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        #[test]
+        fn neighbour_direction_between_adjacent_cells() {
+            let cell = Vec2::new(2.0, 3.0);
+            assert_eq!(neighbour_direction(cell, Vec2::new(3.0, 3.0)), Some(SnakeDirection::RIGHT));
+            assert_eq!(neighbour_direction(cell, Vec2::new(1.0, 3.0)), Some(SnakeDirection::LEFT));
+            assert_eq!(neighbour_direction(cell, Vec2::new(2.0, 4.0)), Some(SnakeDirection::UP));
+            assert_eq!(neighbour_direction(cell, Vec2::new(2.0, 2.0)), Some(SnakeDirection::DOWN));
+        }
+
+        #[test]
+        fn neighbour_direction_across_a_wrap() {
+            assert_eq!(neighbour_direction(Vec2::new(9.0, 0.0), Vec2::new(-9.0, 0.0)), Some(SnakeDirection::RIGHT));
+            assert_eq!(neighbour_direction(Vec2::new(0.0, -9.0), Vec2::new(0.0, 9.0)), Some(SnakeDirection::DOWN));
+        }
+
+        #[test]
+        fn neighbour_direction_of_the_same_cell() {
+            assert_eq!(neighbour_direction(Vec2::new(1.0, 1.0), Vec2::new(1.0, 1.0)), None);
+        }
+    }
+}
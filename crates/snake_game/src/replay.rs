@@ -0,0 +1,135 @@
+pub mod replay_data {
+    use serde::{Deserialize, Serialize};
+    use std::path::PathBuf;
+    use crate::game::game_data::*;
+    use crate::level::level_data::*;
+    use crate::snake::snake_data::*;
+
+    /// A recorded round. Fruit placement only depends on `seed`, so playing
+    /// `moves` back on the same board reproduces the round exactly.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Replay {
+        pub seed: u64,
+        pub play_area: f32,
+        pub cell_size: f64,
+        pub mode: GameMode,
+        pub difficulty: Difficulty,
+        pub level: Level,
+        /// Direction the snake moved in on each tick.
+        pub moves: Vec<SnakeDirection>,
+    }
+
+    /// Resource: the moves of the current round, written to `path` when the
+    /// round ends. Disabled when `path` is `None`.
+    #[derive(Default)]
+    pub struct ReplayRecorder {
+        pub path: Option<PathBuf>,
+        pub moves: Vec<SnakeDirection>,
+    }
+
+    /// Resource: moves of a loaded replay, applied one per tick in place of
+    /// the player's input.
+    #[derive(Default)]
+    pub struct ReplayPlayer {
+        pub moves: Vec<SnakeDirection>,
+        pub cursor: usize,
+    }
+}
+
+pub mod replay_functions {
+    use bevy::prelude::*;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use super::replay_data::*;
+    use crate::game::game_data::*;
+    use crate::level::level_data::*;
+    use crate::snake::snake_data::*;
+
+    impl Replay {
+        pub fn load(path: &Path) -> Result<Replay, String> {
+            let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+            ron::de::from_str(&text).map_err(|err| format!("invalid replay `{}`: {}", path.display(), err))
+        }
+
+        pub fn save(&self, path: &Path) -> Result<(), String> {
+            let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|err| err.to_string())?;
+            fs::write(path, text).map_err(|err| format!("could not write `{}`: {}", path.display(), err))
+        }
+    }
+
+    impl ReplayRecorder {
+        pub fn new(path: Option<PathBuf>) -> ReplayRecorder {
+            ReplayRecorder { path, moves: Vec::new() }
+        }
+
+        /// Called by `snake_movement` with the direction of every move.
+        pub fn record(&mut self, direction: SnakeDirection) {
+            if self.path.is_some() {
+                self.moves.push(direction);
+            }
+        }
+    }
+
+    impl ReplayPlayer {
+        pub fn new(replay: &Replay) -> ReplayPlayer {
+            ReplayPlayer { moves: replay.moves.clone(), cursor: 0 }
+        }
+
+        /// True when a replay has been loaded to play back.
+        pub fn is_loaded(&self) -> bool {
+            !self.moves.is_empty()
+        }
+
+        /// The recorded direction for the next move, until the replay runs out.
+        pub fn next_move(&mut self) -> Option<SnakeDirection> {
+            let next = self.moves.get(self.cursor).cloned();
+            if next.is_some() {
+                self.cursor += 1;
+            }
+            next
+        }
+    }
+
+    /// Starts recording and playback over when a new round starts.
+    pub fn reset_replay(
+        mut restart_reader: Local<EventReader<EventRestart>>,
+        restart_event: Res<Events<EventRestart>>,
+        mut recorder: ResMut<ReplayRecorder>,
+        mut player: ResMut<ReplayPlayer>,
+    ) {
+        if restart_reader.iter(&restart_event).next().is_some() {
+            recorder.moves.clear();
+            player.cursor = 0;
+        }
+    }
+
+    /// Writes the finished round to the recorder's path.
+    pub fn save_replay(
+        mut game_over_reader: Local<EventReader<EventGameOver>>,
+        game_over_event: Res<Events<EventGameOver>>,
+        game: Res<GameState>,
+        rng: Res<GameRng>,
+        level: Res<Level>,
+        recorder: Res<ReplayRecorder>,
+    ) {
+        for _ in game_over_reader.iter(&game_over_event) {
+            let path = match &recorder.path {
+                Some(path) => path,
+                None => continue,
+            };
+            let replay = Replay {
+                seed: rng.seed,
+                play_area: game.play_area,
+                cell_size: game.cell_size,
+                mode: game.mode,
+                difficulty: game.difficulty,
+                level: level.clone(),
+                moves: recorder.moves.clone(),
+            };
+            if let Err(err) = replay.save(path) {
+                println!("Could not save replay: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,298 @@
+pub mod scores_data {
+    use chrono::{DateTime, Utc};
+    use serde::{Deserialize, Serialize};
+    use std::path::PathBuf;
+    use crate::game::game_data::*;
+
+    /// Entries kept per leaderboard.
+    pub const LEADERBOARD_SIZE: usize = 10;
+    pub const INITIALS_LENGTH: usize = 3;
+
+    /// One finished run on a leaderboard.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct LeaderboardEntry {
+        pub initials: String,
+        pub score: usize,
+        /// Snake length, head included, when the run ended.
+        pub length: usize,
+        /// Seconds the run lasted.
+        pub duration: f32,
+        pub mode: GameMode,
+        pub difficulty: Difficulty,
+        pub achieved: DateTime<Utc>,
+    }
+
+    /// Best runs, highest score first. Equal scores keep the order they
+    /// were set in, so an older record is never pushed down by a tie.
+    #[derive(Clone, Debug, Default, Serialize, Deserialize)]
+    pub struct Leaderboard {
+        pub entries: Vec<LeaderboardEntry>,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct ScoreTable {
+        pub mode: GameMode,
+        pub difficulty: Difficulty,
+        pub leaderboard: Leaderboard,
+    }
+
+    /// Resource: every leaderboard, one per game mode and difficulty, saved
+    /// to `path` whenever one changes.
+    #[derive(Default, Serialize, Deserialize)]
+    pub struct HighScores {
+        pub tables: Vec<ScoreTable>,
+        #[serde(skip)]
+        pub path: Option<PathBuf>,
+    }
+
+    /// Resource: a run that made the leaderboard and is waiting for the
+    /// player to enter their initials.
+    #[derive(Default)]
+    pub struct InitialsEntry {
+        pub pending: Option<LeaderboardEntry>,
+        pub letters: [u8; INITIALS_LENGTH],
+        pub cursor: usize,
+    }
+}
+
+pub mod scores_functions {
+    use bevy::prelude::*;
+    use chrono::Utc;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use super::scores_data::*;
+    use crate::game::game_data::*;
+    use crate::events::events_data::*;
+
+    /// `high_scores.ron` in the platform's user data directory.
+    pub fn high_scores_path() -> Option<PathBuf> {
+        dirs::data_dir().map(|dir| dir.join("snake_bevy").join("high_scores.ron"))
+    }
+
+    impl Leaderboard {
+        /// True if a run scoring `score` would get a place on the board.
+        pub fn qualifies(&self, score: usize) -> bool {
+            score > 0
+                && (self.entries.len() < LEADERBOARD_SIZE || score > self.entries[LEADERBOARD_SIZE - 1].score)
+        }
+
+        /// Adds `entry` below every entry with an equal or higher score and
+        /// returns its rank (0 being first), or `None` if it did not make
+        /// the board.
+        pub fn insert(&mut self, entry: LeaderboardEntry) -> Option<usize> {
+            if !self.qualifies(entry.score) {
+                return None;
+            }
+            let rank = self
+                .entries
+                .iter()
+                .position(|existing| entry.score > existing.score)
+                .unwrap_or(self.entries.len());
+            self.entries.insert(rank, entry);
+            self.entries.truncate(LEADERBOARD_SIZE);
+            Some(rank)
+        }
+    }
+
+    impl HighScores {
+        /// Reads the leaderboards saved at `path`. A missing file starts
+        /// empty; an unreadable one is moved aside to `<path>.corrupt` so it
+        /// is not overwritten, and play continues with empty boards.
+        pub fn load(path: Option<PathBuf>) -> HighScores {
+            let mut high_scores = match &path {
+                Some(path) if path.exists() => read_high_scores(path).unwrap_or_else(|err| {
+                    println!("Could not load high scores: {}", err);
+                    let _ = fs::rename(path, path.with_extension("ron.corrupt"));
+                    HighScores::default()
+                }),
+                _ => HighScores::default(),
+            };
+            high_scores.path = path;
+            high_scores
+        }
+
+        /// Writes the leaderboards to `path`, replacing the old file only
+        /// once the new one is fully written.
+        pub fn save(&self) -> Result<(), String> {
+            let path = match &self.path {
+                Some(path) => path,
+                None => return Ok(()),
+            };
+            if let Some(dir) = path.parent() {
+                fs::create_dir_all(dir).map_err(|err| format!("could not create `{}`: {}", dir.display(), err))?;
+            }
+            let text = ron::ser::to_string_pretty(self, ron::ser::PrettyConfig::default())
+                .map_err(|err| err.to_string())?;
+            let temp = path.with_extension("ron.tmp");
+            fs::write(&temp, text).map_err(|err| format!("could not write `{}`: {}", temp.display(), err))?;
+            fs::rename(&temp, path).map_err(|err| format!("could not write `{}`: {}", path.display(), err))
+        }
+
+        pub fn leaderboard(&self, mode: GameMode, difficulty: Difficulty) -> Leaderboard {
+            self.tables
+                .iter()
+                .find(|table| table.mode == mode && table.difficulty == difficulty)
+                .map(|table| table.leaderboard.clone())
+                .unwrap_or_default()
+        }
+
+        pub fn leaderboard_mut(&mut self, mode: GameMode, difficulty: Difficulty) -> &mut Leaderboard {
+            let index = match self
+                .tables
+                .iter()
+                .position(|table| table.mode == mode && table.difficulty == difficulty)
+            {
+                Some(index) => index,
+                None => {
+                    self.tables.push(ScoreTable { mode, difficulty, leaderboard: Leaderboard::default() });
+                    self.tables.len() - 1
+                }
+            };
+            &mut self.tables[index].leaderboard
+        }
+    }
+
+    impl InitialsEntry {
+        pub fn start(&mut self, entry: LeaderboardEntry) {
+            self.pending = Some(entry);
+            self.letters = [b'A'; INITIALS_LENGTH];
+            self.cursor = 0;
+        }
+
+        pub fn initials(&self) -> String {
+            self.letters.iter().map(|letter| *letter as char).collect()
+        }
+    }
+
+    fn read_high_scores(path: &Path) -> Result<HighScores, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+        ron::de::from_str(&text).map_err(|err| format!("`{}` is corrupt: {}", path.display(), err))
+    }
+
+    const LETTER_KEYS: [KeyCode; 26] = [
+        KeyCode::A, KeyCode::B, KeyCode::C, KeyCode::D, KeyCode::E, KeyCode::F, KeyCode::G,
+        KeyCode::H, KeyCode::I, KeyCode::J, KeyCode::K, KeyCode::L, KeyCode::M, KeyCode::N,
+        KeyCode::O, KeyCode::P, KeyCode::Q, KeyCode::R, KeyCode::S, KeyCode::T, KeyCode::U,
+        KeyCode::V, KeyCode::W, KeyCode::X, KeyCode::Y, KeyCode::Z,
+    ];
+
+    /// Asks for the player's initials when a round ends with a score that
+    /// makes its leaderboard.
+    pub fn start_initials(
+        mut round_reader: Local<EventReader<EventRoundEnded>>,
+        round_events: Res<Events<EventRoundEnded>>,
+        game: Res<GameState>,
+        high_scores: Res<HighScores>,
+        mut entry: ResMut<InitialsEntry>,
+    ) {
+        for event in round_reader.iter(&round_events) {
+            let run = LeaderboardEntry {
+                initials: String::new(),
+                score: event.score,
+                length: event.length,
+                duration: event.duration,
+                mode: game.mode,
+                difficulty: game.difficulty,
+                achieved: Utc::now(),
+            };
+            if high_scores.leaderboard(run.mode, run.difficulty).qualifies(run.score) {
+                entry.start(run);
+            }
+        }
+    }
+
+    /// Arcade-style initials: up/down cycles the letter under the cursor,
+    /// left/right moves the cursor, typing a letter sets it and moves on,
+    /// enter saves the run to the leaderboard.
+    pub fn initials_input(
+        keyboard_input: Res<Input<KeyCode>>,
+        mut entry: ResMut<InitialsEntry>,
+        mut high_scores: ResMut<HighScores>,
+    ) {
+        if entry.pending.is_none() {
+            return;
+        }
+        let cursor = entry.cursor;
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            entry.letters[cursor] = if entry.letters[cursor] == b'Z' { b'A' } else { entry.letters[cursor] + 1 };
+        }
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            entry.letters[cursor] = if entry.letters[cursor] == b'A' { b'Z' } else { entry.letters[cursor] - 1 };
+        }
+        if keyboard_input.just_pressed(KeyCode::Left) && cursor > 0 {
+            entry.cursor -= 1;
+        }
+        if keyboard_input.just_pressed(KeyCode::Right) && cursor + 1 < INITIALS_LENGTH {
+            entry.cursor += 1;
+        }
+        for (offset, key) in LETTER_KEYS.iter().enumerate() {
+            if keyboard_input.just_pressed(*key) {
+                let cursor = entry.cursor;
+                entry.letters[cursor] = b'A' + offset as u8;
+                entry.cursor = (cursor + 1).min(INITIALS_LENGTH - 1);
+            }
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            let initials = entry.initials();
+            if let Some(mut run) = entry.pending.take() {
+                run.initials = initials;
+                high_scores.leaderboard_mut(run.mode, run.difficulty).insert(run);
+                if let Err(err) = high_scores.save() {
+                    println!("Could not save high scores: {}", err);
+                }
+            }
+        }
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn entry(initials: &str, score: usize) -> LeaderboardEntry {
+            LeaderboardEntry {
+                initials: initials.to_string(),
+                score,
+                length: score + 1,
+                duration: 10.0,
+                mode: GameMode::default(),
+                difficulty: Difficulty::default(),
+                achieved: chrono::Utc::now(),
+            }
+        }
+
+        fn initials(board: &Leaderboard) -> Vec<&str> {
+            board.entries.iter().map(|entry| entry.initials.as_str()).collect()
+        }
+
+        #[test]
+        fn insert_keeps_the_highest_score_first() {
+            let mut board = Leaderboard::default();
+            assert_eq!(board.insert(entry("AAA", 5)), Some(0));
+            assert_eq!(board.insert(entry("BBB", 9)), Some(0));
+            assert_eq!(board.insert(entry("CCC", 7)), Some(1));
+            assert_eq!(initials(&board), vec!["BBB", "CCC", "AAA"]);
+        }
+
+        #[test]
+        fn a_tie_goes_below_the_older_record() {
+            let mut board = Leaderboard::default();
+            board.insert(entry("OLD", 5));
+            board.insert(entry("TOP", 8));
+            assert_eq!(board.insert(entry("NEW", 5)), Some(2));
+            assert_eq!(initials(&board), vec!["TOP", "OLD", "NEW"]);
+        }
+
+        #[test]
+        fn a_full_board_drops_the_lowest_and_refuses_ties_with_it() {
+            let mut board = Leaderboard::default();
+            for score in 1..=LEADERBOARD_SIZE {
+                board.insert(entry("AAA", score));
+            }
+            assert_eq!(board.insert(entry("LOW", 1)), None);
+            assert_eq!(board.insert(entry("TOP", 100)), Some(0));
+            assert_eq!(board.entries.len(), LEADERBOARD_SIZE);
+            assert_eq!(board.entries.last().unwrap().score, 2);
+            assert_eq!(board.insert(entry("NIL", 0)), None);
+        }
+    }
+}
@@ -0,0 +1,259 @@
+pub mod sim_data {
+    use bevy::prelude::*;
+    use rand::rngs::StdRng;
+    use crate::snake::snake_data::*;
+
+    /// A snake inside a headless `Simulation`. Mirrors `Snake` + its `Tail`
+    /// segments, with the tail stored head-first.
+    #[derive(Clone, Debug)]
+    pub struct SimSnake {
+        pub position: Vec2,
+        pub direction: SnakeDirection,
+        pub tail: Vec<Vec2>,
+        pub alive: bool,
+        pub score: usize,
+        pub ticks_survived: u32,
+    }
+
+    /// A board-only game of snake, stepped one tick at a time without Bevy.
+    /// Used by tools that need to play many games quickly (tournaments,
+    /// training) and follows the same wall, tail and fruit rules as
+    /// `snake_collision`.
+    pub struct Simulation {
+        pub grid_max: f32,
+        pub snakes: Vec<SimSnake>,
+        pub fruit: Option<Vec2>,
+        pub tick: u32,
+        pub max_ticks: u32,
+        pub rng: StdRng,
+        /// Cells blocked by a level's obstacles.
+        pub obstacles: Vec<Vec2>,
+        /// Snakes leaving the board come back in on the opposite side, as in
+        /// `GameMode::Wrap`.
+        pub wrap: bool,
+    }
+
+    /// How a single snake finished a simulated game.
+    #[derive(Clone, Debug)]
+    pub struct SimResult {
+        pub length: usize,
+        pub score: usize,
+        pub ticks_survived: u32,
+        pub alive: bool,
+        /// Finishing place, 0 being the winner. Snakes that could not be
+        /// separated share a place.
+        pub place: usize,
+    }
+}
+
+pub mod sim_functions {
+    use bevy::prelude::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use super::sim_data::*;
+    use crate::snake::snake_data::*;
+    use crate::snake::snake_functions::*;
+
+    impl SimSnake {
+        pub fn new(position: Vec2, direction: SnakeDirection) -> SimSnake {
+            SimSnake {
+                position,
+                direction,
+                tail: Vec::new(),
+                alive: true,
+                score: 0,
+                ticks_survived: 0,
+            }
+        }
+
+        pub fn length(&self) -> usize {
+            self.tail.len() + 1
+        }
+
+        /// True if `position` is covered by this snake's head or tail.
+        pub fn occupies(&self, position: Vec2) -> bool {
+            self.position == position || self.tail.contains(&position)
+        }
+    }
+
+    impl Simulation {
+        /// Creates a board matching a `GameState` with `play_area` and
+        /// `cell_size`, with `snake_count` snakes spread over its rows.
+        pub fn new(play_area: f32, cell_size: f64, snake_count: usize, seed: u64, max_ticks: u32) -> Simulation {
+            let grid_max = grid_max(play_area, cell_size);
+            let rows = (grid_max * 2.0 - 1.0).max(1.0);
+            let mut snakes = Vec::new();
+            for i in 0..snake_count {
+                let y = if snake_count == 1 {
+                    -6.0_f32.max(-grid_max + 1.0)
+                } else {
+                    (-grid_max + 1.0 + (i as f32 + 0.5) * rows / snake_count as f32).floor()
+                };
+                let snake = if i % 2 == 0 {
+                    SimSnake::new(Vec2::new((-grid_max / 2.0).round(), y), SnakeDirection::RIGHT)
+                } else {
+                    SimSnake::new(Vec2::new((grid_max / 2.0).round(), y), SnakeDirection::LEFT)
+                };
+                snakes.push(snake);
+            }
+            let mut sim = Simulation {
+                grid_max,
+                snakes,
+                fruit: None,
+                tick: 0,
+                max_ticks,
+                rng: StdRng::seed_from_u64(seed),
+                obstacles: Vec::new(),
+                wrap: false,
+            };
+            sim.spawn_fruit();
+            sim
+        }
+
+        /// Plays on a board with `obstacles`, wrapping around the edges if
+        /// `wrap` is set. The fruit is placed again in case it landed on an
+        /// obstacle.
+        pub fn with_rules(mut self, obstacles: Vec<Vec2>, wrap: bool) -> Simulation {
+            self.obstacles = obstacles;
+            self.wrap = wrap;
+            self.fruit = None;
+            self.spawn_fruit();
+            self
+        }
+
+        pub fn alive_count(&self) -> usize {
+            self.snakes.iter().filter(|snake| snake.alive).count()
+        }
+
+        /// A game ends once every snake is dead, once only one snake is left
+        /// standing in a multi-snake game, or when `max_ticks` is reached.
+        pub fn is_finished(&self) -> bool {
+            let alive = self.alive_count();
+            alive == 0
+                || (self.snakes.len() > 1 && alive <= 1)
+                || (self.max_ticks > 0 && self.tick >= self.max_ticks)
+        }
+
+        /// True if `position` is outside the board or covered by any live
+        /// snake or obstacle.
+        pub fn is_blocked(&self, position: Vec2) -> bool {
+            hits_wall(position, self.grid_max)
+                || self.obstacles.contains(&position)
+                || self.snakes.iter().any(|snake| snake.alive && snake.occupies(position))
+        }
+
+        /// Advances the game by one tick. `moves[i]` is the direction
+        /// requested for snake `i`; reversing into the tail is ignored, the
+        /// same way `snake_movement` ignores it.
+        pub fn step(&mut self, moves: &[SnakeDirection]) {
+            if self.is_finished() {
+                return;
+            }
+            self.tick += 1;
+            let mut grow_at: Vec<Option<Vec2>> = vec![None; self.snakes.len()];
+            for (i, snake) in self.snakes.iter_mut().enumerate() {
+                if !snake.alive {
+                    continue;
+                }
+                if let Some(next_move) = moves.get(i) {
+                    if *next_move != snake.direction.opposite() {
+                        snake.direction = *next_move;
+                    }
+                }
+                let last_position = snake.position;
+                grow_at[i] = Some(*snake.tail.last().unwrap_or(&last_position));
+                snake.position = snake.direction.step(snake.position);
+                if self.wrap {
+                    snake.position = wrap_position(snake.position, self.grid_max);
+                }
+                if !snake.tail.is_empty() {
+                    snake.tail.pop();
+                    snake.tail.insert(0, last_position);
+                }
+            }
+
+            let mut eaten = false;
+            for (i, snake) in self.snakes.iter_mut().enumerate() {
+                if !snake.alive {
+                    continue;
+                }
+                if self.fruit == Some(snake.position) {
+                    snake.score += 1;
+                    if let Some(segment) = grow_at[i] {
+                        snake.tail.push(segment);
+                    }
+                    eaten = true;
+                }
+            }
+
+            let mut dead = vec![false; self.snakes.len()];
+            for (i, snake) in self.snakes.iter().enumerate() {
+                if !snake.alive {
+                    continue;
+                }
+                if hits_wall(snake.position, self.grid_max) || self.obstacles.contains(&snake.position) {
+                    dead[i] = true;
+                    continue;
+                }
+                for (j, other) in self.snakes.iter().enumerate() {
+                    if !other.alive {
+                        continue;
+                    }
+                    let hit_body = other.tail.contains(&snake.position);
+                    let hit_head = i != j && other.position == snake.position;
+                    if hit_body || hit_head {
+                        dead[i] = true;
+                        break;
+                    }
+                }
+            }
+            for (snake, dead) in self.snakes.iter_mut().zip(dead) {
+                if dead {
+                    snake.alive = false;
+                } else if snake.alive {
+                    snake.ticks_survived = self.tick;
+                }
+            }
+
+            if eaten || self.fruit.is_none() {
+                self.fruit = None;
+                self.spawn_fruit();
+            }
+        }
+
+        /// Places a fruit on a random free cell, leaving the board without
+        /// fruit if none are left.
+        pub fn spawn_fruit(&mut self) {
+            let max = self.grid_max as i32 - 1;
+            let mut free = Vec::new();
+            for x in -max..=max {
+                for y in -max..=max {
+                    let position = Vec2::new(x as f32, y as f32);
+                    if !self.is_blocked(position) {
+                        free.push(position);
+                    }
+                }
+            }
+            if !free.is_empty() {
+                let index = self.rng.gen_range(0, free.len());
+                self.fruit = Some(free[index]);
+            }
+        }
+
+        /// Final standings for every snake, in the order they were created.
+        /// Survivors beat the dead, then longer snakes beat shorter ones,
+        /// then the snake that lasted longer wins.
+        pub fn results(&self) -> Vec<SimResult> {
+            let key = |snake: &SimSnake| (snake.alive, snake.length(), snake.ticks_survived);
+            self.snakes
+                .iter()
+                .map(|snake| SimResult {
+                    length: snake.length(),
+                    score: snake.score,
+                    ticks_survived: snake.ticks_survived,
+                    alive: snake.alive,
+                    place: self.snakes.iter().filter(|other| key(other) > key(snake)).count(),
+                })
+                .collect()
+        }
+    }
+}
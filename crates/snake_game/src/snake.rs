@@ -2,125 +2,155 @@ pub mod snake_functions {
     use bevy::prelude::*;
     use super::snake_data::*;
     use crate::game::game_data::*;
+    use crate::dataset::dataset_data::*;
+    use crate::state::state_data::*;
+    use crate::level::level_data::*;
+    use crate::replay::replay_data::*;
+    use crate::events::events_data::*;
+    use crate::bindings::bindings_data::*;
+    use crate::controller::controller_data::*;
 
     pub fn snake_movement(
         time: Res<Time>,
         mut timer: ResMut<GameTimer>,
         keyboard_input: Res<Input<KeyCode>>,
+        bindings: Res<KeyBindings>,
         mut move_tail: ResMut<Events<EventMoveTail>>,
+        mut turned: ResMut<Events<EventSnakeTurned>>,
+        mut dataset: ResMut<DatasetRecorder>,
+        mut stats: ResMut<RoundStats>,
+        mut recorder: ResMut<ReplayRecorder>,
+        mut player: ResMut<ReplayPlayer>,
+        pilot: Res<Pilot>,
         game: Res<GameState>,
-        mut query: Query<(&mut Snake, &mut Transform)>,
+        flow: Res<GameFlow>,
+        mut query: Query<(Entity, &mut Snake)>,
     ){
-        timer.0.tick(time.delta_seconds);
-        if timer.0.finished && game.playing {
-            for (mut snake, mut transform) in query.iter_mut() {
-            
-                snake.last_position = snake.position;
-                match snake.direction {
-                    SnakeDirection::UP => snake.position = Vec2::new(snake.position.x(), snake.position.y() + 1.0),
-                    SnakeDirection::LEFT => snake.position = Vec2::new(snake.position.x() - 1.0, snake.position.y()),
-                    SnakeDirection::RIGHT => snake.position = Vec2::new(snake.position.x() + 1.0, snake.position.y()),
-                    SnakeDirection::DOWN => snake.position = Vec2::new(snake.position.x(), snake.position.y() - 1.0),
-                }
-                transform.translation = snake_pos_to_translation(snake.position, game.cell_size);
-                move_tail.send(EventMoveTail{ position: snake.last_position });
-                snake.movement_locked = false;
-            }
+        if !flow.is(AppState::Playing) {
+            return;
         }
-        for (mut snake, _) in query.iter_mut() {
-            if  keyboard_input.pressed(KeyCode::Left) {
-                match snake.direction {
-                    SnakeDirection::RIGHT => (),
-                    _ => {
-                            snake.next_move = SnakeDirection::LEFT;
-                        }
-                }
+        // A pilot or replay steers on its own; turns from the player's
+        // keys, pad or pointer are thrown away rather than mixed in.
+        let steered = pilot.0.is_some() || player.is_loaded();
+        if steered {
+            for (_, mut snake) in query.iter_mut() {
+                snake.moves.clear();
             }
-    
-            if  keyboard_input.pressed(KeyCode::Right) {
-                match snake.direction {
-                    SnakeDirection::LEFT => (),
-                    _ => {
-                            snake.next_move = SnakeDirection::RIGHT;
+        } else if bindings.steering.uses_direction_keys() {
+            for (_, mut snake) in query.iter_mut() {
+                for action in Action::ALL.iter() {
+                    if let Some(direction) = action.direction() {
+                        if bindings.just_pressed(*action, &keyboard_input) {
+                            snake.queue_move(direction);
                         }
+                    }
                 }
             }
-    
-            if  keyboard_input.pressed(KeyCode::Down) {
-                match snake.direction {
-                    SnakeDirection::UP => (),
-                    _ => {
-                            snake.next_move = SnakeDirection::DOWN;
-                        }
+        }
+        let speed = if bindings.pressed(Action::Boost, &keyboard_input) { BOOST_SPEED } else { 1.0 };
+        timer.0.tick(time.delta_seconds * speed);
+        if timer.0.finished {
+            stats.ticks += 1;
+            for (snake_entity, mut snake) in query.iter_mut() {
+                if let Some(direction) = snake.moves.pop_front() {
+                    snake.direction = direction;
                 }
-            }
-    
-            if  keyboard_input.pressed(KeyCode::Up) {
-                match snake.direction {
-                    SnakeDirection::DOWN => (),
-                    _ => {
-                            snake.next_move = SnakeDirection::UP;
-                        }
+                if let Some(direction) = player.next_move() {
+                    snake.direction = direction;
+                }
+                if !steered {
+                    dataset.commit(snake.direction);
+                }
+                recorder.record(snake.direction);
+                snake.last_position = snake.position;
+                snake.position = snake.direction.step(snake.position);
+                if game.mode == GameMode::Wrap {
+                    snake.position = wrap_position(snake.position, grid_max(game.play_area, game.cell_size));
+                }
+                if snake.direction != snake.last_direction {
+                    turned.send(EventSnakeTurned{
+                        snake: snake_entity,
+                        from: snake.last_direction,
+                        to: snake.direction,
+                        position: snake.position,
+                        tick: stats.ticks,
+                    });
+                    snake.last_direction = snake.direction;
                 }
+                move_tail.send(EventMoveTail{ position: snake.last_position });
             }
-    
-            if !snake.movement_locked {
-                snake.direction = snake.next_move;
-                snake.movement_locked = true;
+        }
+    }
+
+    impl Snake {
+        /// Queues a turn for the coming ticks. Turns are dropped once
+        /// `MAX_QUEUED_MOVES` are waiting, and so are turns that would not
+        /// change direction or would reverse the snake into its own tail.
+        pub fn queue_move(&mut self, direction: SnakeDirection) -> bool {
+            let heading = *self.moves.back().unwrap_or(&self.direction);
+            if self.moves.len() >= MAX_QUEUED_MOVES || direction == heading || direction == heading.opposite() {
+                return false;
             }
+            self.moves.push_back(direction);
+            true
         }
     }
 
     pub fn snake_collision(
         mut commands: Commands,
         timer: Res<GameTimer>,
+        flow: Res<GameFlow>,
         mut game: ResMut<GameState>,
+        mut stats: ResMut<RoundStats>,
+        level: Res<Level>,
         mut grow_tail: ResMut<Events<EventGrowTail>>,
         mut game_over: ResMut<Events<EventGameOver>>,
+        mut fruit_eaten: ResMut<Events<EventFruitEaten>>,
+        mut score_changed: ResMut<Events<EventScoreChanged>>,
+        mut snake_died: ResMut<Events<EventSnakeDied>>,
         mut snake_query: Query<(Entity, &mut Snake)>,
         tail_query: Query<(Entity, &Tail)>,
-        collider_query: Query<(Entity, &Collider, &Transform)>,
+        collider_query: Query<(Entity, &Collider)>,
         fruit_query: Query<(Entity, &Fruit)>,
     ){
-        let mut hit = false;
-        if timer.0.finished && game.playing {
-            for (_, snake) in snake_query.iter_mut() {
-                for (_, collider, collider_transform) in collider_query.iter() {
+        let mut death: Option<(Entity, DeathCause, Vec2)> = None;
+        if timer.0.finished && flow.is(AppState::Playing) {
+            for (snake_entity, snake) in snake_query.iter_mut() {
+                if level.blocks(snake.position) {
+                    death = Some((snake_entity, DeathCause::Obstacle, snake.position));
+                }
+                for (_, collider) in collider_query.iter() {
                     match collider {
                         Collider::Snake => {
-                            let grid_max = (game.play_area / game.cell_size as f32 / 2.0).round();
-                            if snake.position.x().abs() == grid_max || snake.position.y().abs() == grid_max{
-                                hit = true;
+                            if hits_wall(snake.position, grid_max(game.play_area, game.cell_size)) {
+                                death = Some((snake_entity, DeathCause::Wall, snake.position));
                             }
                         },
                         Collider::Tail => {
                             for (_, tail_segment) in tail_query.iter(){
                                 if snake.position.x() == tail_segment.position.x() && snake.position.y() == tail_segment.position.y() {
-                                    hit = true;
+                                    death = Some((snake_entity, DeathCause::OwnTail, snake.position));
                                 }
                             }
                         },
                         Collider::Fruit => {
-                            let fruit_x = (collider_transform.translation.x() / game.cell_size as f32).round();
-                            let fruit_y = (collider_transform.translation.y() / game.cell_size as f32).round();
-                            if fruit_x == snake.position.x() && fruit_y == snake.position.y(){
-                                game.score += 1;
-                                grow_tail.send(EventGrowTail{});
-                                for (fruit_entity, _) in fruit_query.iter() {
-                                    commands.despawn(fruit_entity);
-                                }
-                                println!(" S C O R E : {} !", game.score);
-                                break;
-                            }
-                            for (_, segment) in tail_query.iter() {
-                                if fruit_x == segment.position.x() && fruit_y == segment.position.y(){
+                            for (fruit_entity, fruit) in fruit_query.iter() {
+                                if fruit.position == snake.position {
                                     game.score += 1;
+                                    fruit_eaten.send(EventFruitEaten{
+                                        snake: snake_entity,
+                                        kind: fruit.kind,
+                                        position: fruit.position,
+                                        tick: stats.ticks,
+                                    });
+                                    score_changed.send(EventScoreChanged{
+                                        previous: game.score - 1,
+                                        score: game.score,
+                                    });
                                     grow_tail.send(EventGrowTail{});
-                                    for (fruit_entity, _) in fruit_query.iter() {
-                                        commands.despawn(fruit_entity);
-                                    }
+                                    stats.eat(fruit.kind);
+                                    commands.despawn(fruit_entity);
                                     println!(" S C O R E : {} !", game.score);
-                                    break;
                                 }
                             }
                         }
@@ -129,27 +159,67 @@ pub mod snake_functions {
                 }
             }
         }
-        if hit {
-            game_over.send(EventGameOver{});
+        if let Some((snake, cause, impact)) = death {
+            let length = tail_query.iter().len() + 1;
+            snake_died.send(EventSnakeDied{
+                snake,
+                cause,
+                position: impact,
+                length,
+                tick: stats.ticks,
+            });
+            game_over.send(EventGameOver{
+                snake,
+                cause,
+                impact,
+                score: game.score,
+                length,
+                tick: stats.ticks,
+            });
         }
     }
 
-    fn snake_pos_to_translation(snake_pos: Vec2, c_size: f64) -> Vec3 {
+    /// Number of cells from the centre of the board to the wall, on either axis.
+    pub fn grid_max(play_area: f32, cell_size: f64) -> f32 {
+        (play_area / cell_size as f32 / 2.0).round()
+    }
+
+    /// True when a grid position lies on or beyond the surrounding wall.
+    pub fn hits_wall(position: Vec2, grid_max: f32) -> bool {
+        position.x().abs() >= grid_max || position.y().abs() >= grid_max
+    }
+
+    /// Brings a position that left the board back in on the opposite side.
+    pub fn wrap_position(position: Vec2, grid_max: f32) -> Vec2 {
+        let wrap = |value: f32| {
+            if value >= grid_max {
+                -grid_max + 1.0
+            } else if value <= -grid_max {
+                grid_max - 1.0
+            } else {
+                value
+            }
+        };
+        Vec2::new(wrap(position.x()), wrap(position.y()))
+    }
+
+    pub fn snake_pos_to_translation(snake_pos: Vec2, c_size: f64) -> Vec3 {
         return Vec3::new((snake_pos.x() * c_size as f32).floor(), (snake_pos.y() * c_size as f32).floor(), 0.0);
     }
     
     pub fn move_tail_listener(
         mut move_reader: Local<EventReader<EventMoveTail>>,
         move_event: Res<Events<EventMoveTail>>,
-        mut tail_query: Query<(&mut Tail, &mut Transform)>,
+        mut tail_query: Query<&mut Tail>,
     ){
         for move_event in move_reader.iter(&move_event){
             let mut last_pos = move_event.position;
-            for (mut segment, mut segment_transform) in tail_query.iter_mut(){
+            let mut segments: Vec<Mut<Tail>> = tail_query.iter_mut().collect();
+            segments.sort_by_key(|segment| segment.index);
+            for segment in segments.iter_mut(){
                 let next_pos = segment.position;
                 segment.position = last_pos;
                 last_pos = next_pos;
-                segment_transform.translation = Vec3::new(segment.position.x() * 25.0, segment.position.y() * 25.0, 0.0);
             }
         }
     }
@@ -157,46 +227,117 @@ pub mod snake_functions {
     pub fn grow_tail_listener(
         mut commands: Commands,
         mut grow_reader: Local<EventReader<EventGrowTail>>,
-        game: Res<GameState>,
-        mut materials: ResMut<Assets<ColorMaterial>>,
         grow_event: Res<Events<EventGrowTail>>,
         snake_query: Query<&Snake>,
+        tail_query: Query<&Tail>,
     ) {
+        let mut length = tail_query.iter().len();
         for _ in grow_reader.iter(&grow_event){
-            let cell_size = game.cell_size as f32;
             for snake in snake_query.iter(){
-                commands.spawn(SpriteComponents {
-                    material: materials.add(Color::rgb(0.0, 1.0, 0.0).into()),
-                    transform: Transform::from_translation(Vec3::new(
-                        snake.last_position.x() * cell_size,
-                        snake.last_position.y()  * cell_size,
-                        0.0
-                    )),
-                        sprite: Sprite::new(Vec2::new(cell_size - 2.0, cell_size - 2.0)),
-                        ..Default::default()
-                    })
-                    .with(Tail{
-                        position: snake.last_position,
-                    })
-                    .with(Collider::Tail);
+                commands.spawn((
+                        Tail{
+                            position: snake.last_position,
+                            index: length,
+                        },
+                        Collider::Tail,
+                        RoundEntity,
+                    ));
+                length += 1;
+            }
+        }
+    }
+
+    /// Cells of the tail from the head back. Queries do not keep the order
+    /// segments were spawned in, so they are sorted by `Tail::index`.
+    pub fn tail_cells(tail_query: &Query<&Tail>) -> Vec<Vec2> {
+        let mut segments: Vec<&Tail> = tail_query.iter().collect();
+        segments.sort_by_key(|segment| segment.index);
+        segments.iter().map(|segment| segment.position).collect()
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use std::collections::VecDeque;
+
+        fn heading(direction: SnakeDirection) -> Snake {
+            Snake {
+                player: 0,
+                position: Vec2::zero(),
+                last_position: Vec2::zero(),
+                direction,
+                last_direction: direction,
+                moves: VecDeque::new(),
+            }
+        }
+
+        #[test]
+        fn queue_move_keeps_turns_in_order() {
+            let mut snake = heading(SnakeDirection::RIGHT);
+            assert!(snake.queue_move(SnakeDirection::UP));
+            assert!(snake.queue_move(SnakeDirection::LEFT));
+            assert_eq!(snake.moves, vec![SnakeDirection::UP, SnakeDirection::LEFT]);
+        }
+
+        #[test]
+        fn queue_move_is_capped() {
+            let mut snake = heading(SnakeDirection::RIGHT);
+            for direction in [SnakeDirection::UP, SnakeDirection::LEFT, SnakeDirection::DOWN].iter() {
+                assert!(snake.queue_move(*direction));
             }
+            assert!(!snake.queue_move(SnakeDirection::RIGHT));
+            assert_eq!(snake.moves.len(), MAX_QUEUED_MOVES);
+        }
+
+        #[test]
+        fn queue_move_refuses_reversals_and_repeats() {
+            let mut snake = heading(SnakeDirection::RIGHT);
+            assert!(!snake.queue_move(SnakeDirection::LEFT));
+            assert!(!snake.queue_move(SnakeDirection::RIGHT));
+            assert!(snake.queue_move(SnakeDirection::UP));
+            // Checked against the last queued turn, not the current heading.
+            assert!(!snake.queue_move(SnakeDirection::DOWN));
+            assert!(snake.queue_move(SnakeDirection::LEFT));
+            assert_eq!(snake.moves, vec![SnakeDirection::UP, SnakeDirection::LEFT]);
         }
     }
 }
 pub mod snake_data {
     use bevy::prelude::*;
-    pub struct Fruit;
+    use serde::{Deserialize, Serialize};
+    use std::collections::VecDeque;
+
+    /// Most turns a snake holds in its queue at once.
+    pub const MAX_QUEUED_MOVES: usize = 3;
+
+    /// How much faster the snake moves while the boost keys are held.
+    pub const BOOST_SPEED: f32 = 2.0;
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub enum FruitKind {
+        Apple,
+    }
+
+    pub struct Fruit {
+        pub kind: FruitKind,
+        pub position: Vec2,
+    }
 
     pub struct Snake {
+        /// Which player steers this snake, counting from 0.
+        pub player: usize,
         pub position: Vec2,
         pub last_position: Vec2,
         pub direction: SnakeDirection,
-        pub movement_locked: bool,
-        pub next_move: SnakeDirection,
+        /// Direction of the last move made, to tell when the snake turns.
+        pub last_direction: SnakeDirection,
+        /// Turns waiting to be made, one per tick, oldest first.
+        pub moves: VecDeque<SnakeDirection>,
     }
 
     pub struct Tail {
         pub position: Vec2,
+        /// Place along the snake, 0 being the segment behind the head.
+        pub index: usize,
     }
 
     pub struct EventGrowTail {
@@ -204,13 +345,40 @@ pub mod snake_data {
     pub struct EventMoveTail {
         pub position: Vec2,
     }
-    #[derive(Copy, Clone)]
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
     pub enum SnakeDirection {
         UP,
         DOWN,
         LEFT,
         RIGHT
     }
+    impl SnakeDirection {
+        pub const ALL: [SnakeDirection; 4] = [
+            SnakeDirection::UP,
+            SnakeDirection::DOWN,
+            SnakeDirection::LEFT,
+            SnakeDirection::RIGHT,
+        ];
+
+        pub fn opposite(self) -> SnakeDirection {
+            match self {
+                SnakeDirection::UP => SnakeDirection::DOWN,
+                SnakeDirection::DOWN => SnakeDirection::UP,
+                SnakeDirection::LEFT => SnakeDirection::RIGHT,
+                SnakeDirection::RIGHT => SnakeDirection::LEFT,
+            }
+        }
+
+        /// The grid cell one step from `position` in this direction.
+        pub fn step(self, position: Vec2) -> Vec2 {
+            match self {
+                SnakeDirection::UP => Vec2::new(position.x(), position.y() + 1.0),
+                SnakeDirection::DOWN => Vec2::new(position.x(), position.y() - 1.0),
+                SnakeDirection::LEFT => Vec2::new(position.x() - 1.0, position.y()),
+                SnakeDirection::RIGHT => Vec2::new(position.x() + 1.0, position.y()),
+            }
+        }
+    }
     pub enum Collider {
         Solid,
         Snake,
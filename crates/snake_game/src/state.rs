@@ -0,0 +1,414 @@
+pub mod state_data {
+    /// Name of the stage, run before `stage::UPDATE`, where requested state
+    /// changes take effect.
+    pub const STATE_TRANSITION: &str = "state_transition";
+
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Hash)]
+    pub enum AppState {
+        Title,
+        Playing,
+        Paused,
+        GameOver,
+        Settings,
+        Leaderboard,
+    }
+
+    /// Resource: which screen the game is on. Systems ask for a new state
+    /// with `GameFlow::set`; the change happens at the start of the next
+    /// frame and is announced with an `EventStateChange`.
+    pub struct GameFlow {
+        pub current: AppState,
+        /// State to go back to when leaving a menu screen.
+        pub previous: AppState,
+        pub next: Option<AppState>,
+    }
+
+    /// Sent once per state change. Systems that need to run when a state is
+    /// entered or exited listen for this and check `entered`/`exited`.
+    pub struct EventStateChange {
+        pub from: AppState,
+        pub to: AppState,
+    }
+
+    /// Pause menu entries, top to bottom.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum PauseItem {
+        Resume,
+        Restart,
+        Settings,
+        Quit,
+    }
+
+    /// Resource: the highlighted entry on the pause menu.
+    #[derive(Default)]
+    pub struct PauseMenu {
+        pub cursor: usize,
+    }
+
+    /// Settings screen entries, top to bottom.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq)]
+    pub enum SettingsItem {
+        Mode,
+        Difficulty,
+        Steering,
+        Smooth,
+        Theme,
+        Palette,
+        TailPattern,
+        FruitBurst,
+        DeathExplosion,
+        ScreenShake,
+        Flash,
+        Controls,
+    }
+
+    /// Resource: the highlighted entry on the settings screen.
+    #[derive(Default)]
+    pub struct SettingsMenu {
+        pub cursor: usize,
+    }
+}
+
+pub mod state_functions {
+    use bevy::prelude::*;
+    use bevy::window::WindowFocused;
+    use super::state_data::*;
+    use crate::game::game_data::*;
+    use crate::scores::scores_data::*;
+    use crate::config::config_data::*;
+    use crate::bindings::bindings_data::*;
+    use crate::bindings::bindings_functions::*;
+    use crate::gamepad::gamepad_data::*;
+    use crate::steering::steering_data::*;
+    use crate::theme::theme_data::*;
+
+    impl Default for GameFlow {
+        fn default() -> Self {
+            GameFlow {
+                current: AppState::Title,
+                previous: AppState::Title,
+                next: None,
+            }
+        }
+    }
+
+    impl GameFlow {
+        pub fn is(&self, state: AppState) -> bool {
+            self.current == state
+        }
+
+        /// True while a round is on hold: paused, or on the settings screen
+        /// opened from the pause menu.
+        pub fn is_paused(&self) -> bool {
+            self.is(AppState::Paused) || (self.is(AppState::Settings) && self.previous == AppState::Paused)
+        }
+
+        /// Asks to move to `state` at the start of the next frame.
+        pub fn set(&mut self, state: AppState) {
+            self.next = Some(state);
+        }
+
+        /// Returns to the state the current menu screen was opened from.
+        pub fn back(&mut self) {
+            self.next = Some(self.previous);
+        }
+    }
+
+    impl EventStateChange {
+        pub fn entered(&self, state: AppState) -> bool {
+            self.to == state && self.from != state
+        }
+
+        pub fn exited(&self, state: AppState) -> bool {
+            self.from == state && self.to != state
+        }
+    }
+
+    impl PauseItem {
+        pub const ALL: [PauseItem; 4] = [PauseItem::Resume, PauseItem::Restart, PauseItem::Settings, PauseItem::Quit];
+
+        pub fn label(self) -> &'static str {
+            match self {
+                PauseItem::Resume => "Resume",
+                PauseItem::Restart => "Restart",
+                PauseItem::Settings => "Settings",
+                PauseItem::Quit => "Quit to title",
+            }
+        }
+    }
+
+    impl SettingsItem {
+        pub const ALL: [SettingsItem; 12] = [
+            SettingsItem::Mode,
+            SettingsItem::Difficulty,
+            SettingsItem::Steering,
+            SettingsItem::Smooth,
+            SettingsItem::Theme,
+            SettingsItem::Palette,
+            SettingsItem::TailPattern,
+            SettingsItem::FruitBurst,
+            SettingsItem::DeathExplosion,
+            SettingsItem::ScreenShake,
+            SettingsItem::Flash,
+            SettingsItem::Controls,
+        ];
+
+        pub fn label(self) -> &'static str {
+            match self {
+                SettingsItem::Mode => "Mode",
+                SettingsItem::Difficulty => "Difficulty",
+                SettingsItem::Steering => "Steering",
+                SettingsItem::Smooth => "Smooth movement",
+                SettingsItem::Theme => "Theme",
+                SettingsItem::Palette => "Colors",
+                SettingsItem::TailPattern => "Tail pattern",
+                SettingsItem::FruitBurst => "Fruit bursts",
+                SettingsItem::DeathExplosion => "Death explosion",
+                SettingsItem::ScreenShake => "Screen shake",
+                SettingsItem::Flash => "Game over flash",
+                SettingsItem::Controls => "Controls",
+            }
+        }
+
+        /// Settings that change the rules of a round, which can't be
+        /// changed while one is paused.
+        pub fn is_round_rule(self) -> bool {
+            matches!(self, SettingsItem::Mode | SettingsItem::Difficulty)
+        }
+    }
+
+    pub fn apply_state_transitions(
+        mut flow: ResMut<GameFlow>,
+        mut state_change: ResMut<Events<EventStateChange>>,
+    ) {
+        if let Some(next) = flow.next.take() {
+            if next == flow.current {
+                return;
+            }
+            let from = flow.current;
+            flow.previous = from;
+            flow.current = next;
+            state_change.send(EventStateChange { from, to: next });
+        }
+    }
+
+    /// Enter, the restart keys or the restart button start a game, Tab
+    /// opens the leaderboard, S the settings.
+    pub fn title_input(
+        keyboard_input: Res<Input<KeyCode>>,
+        bindings: Res<KeyBindings>,
+        buttons: Res<Input<GamepadButton>>,
+        gamepads: Res<GamepadAssignments>,
+        mut flow: ResMut<GameFlow>,
+        mut restart: ResMut<Events<EventRestart>>,
+    ) {
+        if !flow.is(AppState::Title) {
+            return;
+        }
+        if keyboard_input.just_pressed(KeyCode::Return)
+            || bindings.just_pressed(Action::Restart, &keyboard_input)
+            || gamepads.just_pressed(&buttons, RESTART_BUTTON)
+        {
+            restart.send(EventRestart {});
+        } else if keyboard_input.just_pressed(KeyCode::Tab) {
+            flow.set(AppState::Leaderboard);
+        } else if keyboard_input.just_pressed(KeyCode::S) {
+            flow.set(AppState::Settings);
+        }
+    }
+
+    /// The pause keys and the pause button pause and resume the game, and
+    /// escape also resumes it. While paused, up/down picks a pause menu entry and enter chooses
+    /// it. Nothing that runs on the game clock is updated outside
+    /// `AppState::Playing`, so the round carries on exactly where it stopped.
+    pub fn pause_input(
+        keyboard_input: Res<Input<KeyCode>>,
+        bindings: Res<KeyBindings>,
+        buttons: Res<Input<GamepadButton>>,
+        gamepads: Res<GamepadAssignments>,
+        mut flow: ResMut<GameFlow>,
+        mut menu: ResMut<PauseMenu>,
+        mut restart: ResMut<Events<EventRestart>>,
+    ) {
+        let toggle = bindings.just_pressed(Action::Pause, &keyboard_input) || gamepads.just_pressed(&buttons, PAUSE_BUTTON);
+        if flow.is(AppState::Playing) {
+            if toggle {
+                menu.cursor = 0;
+                flow.set(AppState::Paused);
+            }
+            return;
+        }
+        if !flow.is(AppState::Paused) {
+            return;
+        }
+        if toggle || keyboard_input.just_pressed(KeyCode::Escape) {
+            flow.set(AppState::Playing);
+            return;
+        }
+        let count = PauseItem::ALL.len();
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            menu.cursor = (menu.cursor + count - 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            menu.cursor = (menu.cursor + 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) {
+            match PauseItem::ALL[menu.cursor] {
+                PauseItem::Resume => flow.set(AppState::Playing),
+                PauseItem::Restart => restart.send(EventRestart {}),
+                PauseItem::Settings => flow.set(AppState::Settings),
+                PauseItem::Quit => flow.set(AppState::Title),
+            }
+        }
+    }
+
+    /// Pauses a running game when the window loses focus.
+    pub fn pause_on_focus_lost(
+        mut focus_reader: Local<EventReader<WindowFocused>>,
+        focus_events: Res<Events<WindowFocused>>,
+        mut flow: ResMut<GameFlow>,
+        mut menu: ResMut<PauseMenu>,
+    ) {
+        for event in focus_reader.iter(&focus_events) {
+            if !event.focused && flow.is(AppState::Playing) {
+                menu.cursor = 0;
+                flow.set(AppState::Paused);
+            }
+        }
+    }
+
+    /// Once any initials are entered: the restart keys or button retry,
+    /// Tab opens the leaderboard and escape returns to the title screen.
+    pub fn game_over_input(
+        keyboard_input: Res<Input<KeyCode>>,
+        bindings: Res<KeyBindings>,
+        buttons: Res<Input<GamepadButton>>,
+        gamepads: Res<GamepadAssignments>,
+        entry: Res<InitialsEntry>,
+        mut flow: ResMut<GameFlow>,
+        mut restart: ResMut<Events<EventRestart>>,
+    ) {
+        if !flow.is(AppState::GameOver) || entry.pending.is_some() {
+            return;
+        }
+        if bindings.just_pressed(Action::Restart, &keyboard_input) || gamepads.just_pressed(&buttons, RESTART_BUTTON) {
+            restart.send(EventRestart {});
+        } else if keyboard_input.just_pressed(KeyCode::Tab) {
+            flow.set(AppState::Leaderboard);
+        } else if keyboard_input.just_pressed(KeyCode::Escape) {
+            flow.set(AppState::Title);
+        }
+    }
+
+    /// Tab or escape closes the leaderboard.
+    pub fn leaderboard_input(
+        keyboard_input: Res<Input<KeyCode>>,
+        mut flow: ResMut<GameFlow>,
+    ) {
+        if flow.is(AppState::Leaderboard)
+            && (keyboard_input.just_pressed(KeyCode::Tab) || keyboard_input.just_pressed(KeyCode::Escape))
+        {
+            flow.back();
+        }
+    }
+
+    /// Up/down picks an entry, left/right changes it, enter opens the
+    /// controls screen and escape goes back.
+    pub fn settings_input(
+        keyboard_input: Res<Input<KeyCode>>,
+        mut flow: ResMut<GameFlow>,
+        mut menu: ResMut<SettingsMenu>,
+        mut controls: ResMut<ControlsMenu>,
+        mut bindings: ResMut<KeyBindings>,
+        mut game: ResMut<GameState>,
+        mut config: ResMut<GameConfig>,
+        config_path: Res<ConfigPath>,
+        mut timer: ResMut<GameTimer>,
+        mut themes: ResMut<Themes>,
+        mut theme_changed: ResMut<Events<EventThemeChanged>>,
+    ) {
+        if !flow.is(AppState::Settings) {
+            return;
+        }
+        if controls.open {
+            edit_controls(&keyboard_input, &mut controls, &mut bindings, &mut config, &config_path.0);
+            return;
+        }
+        if keyboard_input.just_pressed(KeyCode::Escape) {
+            flow.back();
+            return;
+        }
+        let count = SettingsItem::ALL.len();
+        if keyboard_input.just_pressed(KeyCode::Up) {
+            menu.cursor = (menu.cursor + count - 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Down) {
+            menu.cursor = (menu.cursor + 1) % count;
+        }
+        if keyboard_input.just_pressed(KeyCode::Return) && SettingsItem::ALL[menu.cursor] == SettingsItem::Controls {
+            controls.open = true;
+            controls.cursor = 0;
+            return;
+        }
+        let step: i32 = if keyboard_input.just_pressed(KeyCode::Right) {
+            1
+        } else if keyboard_input.just_pressed(KeyCode::Left) {
+            -1
+        } else {
+            return;
+        };
+        let item = SettingsItem::ALL[menu.cursor];
+        if item.is_round_rule() && flow.is_paused() {
+            return;
+        }
+        match item {
+            SettingsItem::Mode => {
+                game.mode = match game.mode {
+                    GameMode::Classic => GameMode::Wrap,
+                    GameMode::Wrap => GameMode::Classic,
+                };
+            }
+            SettingsItem::Difficulty => {
+                let levels = [Difficulty::Easy, Difficulty::Normal, Difficulty::Hard];
+                let index = levels.iter().position(|level| *level == game.difficulty).unwrap_or(1) as i32;
+                game.difficulty = levels[(index + step).clamp(0, 2) as usize];
+                timer.0.duration = config.tick_seconds.get(game.difficulty);
+            }
+            SettingsItem::Steering => {
+                let modes = SteeringMode::ALL;
+                let index = modes.iter().position(|mode| *mode == bindings.steering).unwrap_or(0) as i32;
+                bindings.steering = modes[(index + step).rem_euclid(modes.len() as i32) as usize];
+                config.controls.steering = bindings.steering;
+                if let Err(err) = config.save_settings(&config_path.0) {
+                    println!("Could not save controls: {}", err);
+                }
+            }
+            SettingsItem::Smooth => config.display.smooth_movement = !config.display.smooth_movement,
+            SettingsItem::Theme => {
+                themes.cycle(step);
+                config.display.theme = themes.current().name.clone();
+            }
+            SettingsItem::Palette => {
+                let palettes = ColorVision::ALL;
+                let index = palettes.iter().position(|vision| *vision == config.display.color_vision).unwrap_or(0) as i32;
+                config.display.color_vision = palettes[(index + step).rem_euclid(palettes.len() as i32) as usize];
+            }
+            SettingsItem::TailPattern => config.display.tail_pattern = !config.display.tail_pattern,
+            SettingsItem::FruitBurst => config.display.effects.fruit_burst = !config.display.effects.fruit_burst,
+            SettingsItem::DeathExplosion => {
+                config.display.effects.death_explosion = !config.display.effects.death_explosion;
+            }
+            SettingsItem::ScreenShake => config.display.effects.screen_shake = !config.display.effects.screen_shake,
+            SettingsItem::Flash => config.display.effects.flash = !config.display.effects.flash,
+            SettingsItem::Controls => (),
+        }
+        if matches!(item, SettingsItem::Theme | SettingsItem::Palette | SettingsItem::TailPattern) {
+            theme_changed.send(EventThemeChanged {});
+        }
+        if !matches!(item, SettingsItem::Mode | SettingsItem::Difficulty | SettingsItem::Steering | SettingsItem::Controls) {
+            if let Err(err) = config.save_settings(&config_path.0) {
+                println!("Could not save settings: {}", err);
+            }
+        }
+    }
+}
@@ -0,0 +1,149 @@
+pub mod steering_data {
+    use serde::{Deserialize, Serialize};
+
+    /// Seconds the switch must be held, in `SteeringMode::OneSwitch`, to
+    /// turn right instead of left.
+    pub const SWITCH_HOLD_SECONDS: f32 = 0.3;
+
+    /// How the player's keys steer the snake.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum SteeringMode {
+        /// Each direction has its own keys.
+        Absolute,
+        /// The left and right keys turn the snake a quarter turn from the
+        /// way it is heading.
+        Relative,
+        /// A single switch: a tap turns left, holding it for
+        /// `SWITCH_HOLD_SECONDS` turns right.
+        OneSwitch,
+        /// The mouse and touchscreen steer, as well as the direction keys.
+        Pointer,
+    }
+
+    /// How long the switch has been held in `SteeringMode::OneSwitch`.
+    #[derive(Default)]
+    pub struct SwitchState {
+        pub held: Option<f32>,
+        /// Set once a hold has turned the snake, so releasing the switch
+        /// does not turn it again.
+        pub turned: bool,
+    }
+}
+
+pub mod steering_functions {
+    use bevy::prelude::*;
+    use super::steering_data::*;
+    use crate::bindings::bindings_data::*;
+    use crate::snake::snake_data::*;
+    use crate::state::state_data::*;
+
+    impl Default for SteeringMode {
+        fn default() -> Self {
+            SteeringMode::Absolute
+        }
+    }
+
+    impl SteeringMode {
+        pub const ALL: [SteeringMode; 4] = [
+            SteeringMode::Absolute,
+            SteeringMode::Relative,
+            SteeringMode::OneSwitch,
+            SteeringMode::Pointer,
+        ];
+
+        pub fn label(self) -> &'static str {
+            match self {
+                SteeringMode::Absolute => "Absolute",
+                SteeringMode::Relative => "Relative",
+                SteeringMode::OneSwitch => "One switch",
+                SteeringMode::Pointer => "Mouse / touch",
+            }
+        }
+
+        /// True if each direction key steers straight that way.
+        pub fn uses_direction_keys(self) -> bool {
+            self == SteeringMode::Absolute || self == SteeringMode::Pointer
+        }
+    }
+
+    impl SnakeDirection {
+        /// A quarter turn counter-clockwise.
+        pub fn turn_left(self) -> SnakeDirection {
+            match self {
+                SnakeDirection::UP => SnakeDirection::LEFT,
+                SnakeDirection::LEFT => SnakeDirection::DOWN,
+                SnakeDirection::DOWN => SnakeDirection::RIGHT,
+                SnakeDirection::RIGHT => SnakeDirection::UP,
+            }
+        }
+
+        /// A quarter turn clockwise.
+        pub fn turn_right(self) -> SnakeDirection {
+            self.turn_left().opposite()
+        }
+    }
+
+    impl Snake {
+        /// Queues a quarter turn from the way the snake will be heading once
+        /// the turns already queued are made.
+        pub fn queue_turn(&mut self, clockwise: bool) -> bool {
+            let heading = *self.moves.back().unwrap_or(&self.direction);
+            let direction = if clockwise { heading.turn_right() } else { heading.turn_left() };
+            self.queue_move(direction)
+        }
+    }
+
+    /// Queues turns from the left/right keys in `SteeringMode::Relative`,
+    /// or from the switch in `SteeringMode::OneSwitch`. The turns become
+    /// plain directions in the queue, so replays record them like any
+    /// other move.
+    pub fn relative_steering(
+        time: Res<Time>,
+        keyboard_input: Res<Input<KeyCode>>,
+        bindings: Res<KeyBindings>,
+        flow: Res<GameFlow>,
+        mut switch: Local<SwitchState>,
+        mut query: Query<&mut Snake>,
+    ) {
+        if !flow.is(AppState::Playing) {
+            switch.held = None;
+            return;
+        }
+        let mut turn: Option<bool> = None;
+        match bindings.steering {
+            SteeringMode::Absolute | SteeringMode::Pointer => return,
+            SteeringMode::Relative => {
+                if bindings.just_pressed(Action::Left, &keyboard_input) {
+                    turn = Some(false);
+                } else if bindings.just_pressed(Action::Right, &keyboard_input) {
+                    turn = Some(true);
+                }
+            }
+            SteeringMode::OneSwitch => {
+                if bindings.just_pressed(Action::Switch, &keyboard_input) {
+                    switch.held = Some(0.0);
+                    switch.turned = false;
+                }
+                if let Some(held) = switch.held {
+                    let held = held + time.delta_seconds;
+                    switch.held = Some(held);
+                    if held >= SWITCH_HOLD_SECONDS && !switch.turned {
+                        switch.turned = true;
+                        turn = Some(true);
+                    }
+                    if !bindings.pressed(Action::Switch, &keyboard_input) {
+                        if !switch.turned {
+                            turn = Some(false);
+                        }
+                        switch.held = None;
+                    }
+                }
+            }
+        }
+        if let Some(clockwise) = turn {
+            for mut snake in query.iter_mut() {
+                snake.queue_turn(clockwise);
+            }
+        }
+    }
+}
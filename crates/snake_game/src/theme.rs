@@ -0,0 +1,295 @@
+pub mod theme_data {
+    use serde::{Deserialize, Serialize};
+
+    /// Directory under the asset root the theme files are read from at
+    /// startup, one `.ron` file per theme. Paths inside a theme are
+    /// relative to the asset root.
+    pub const THEMES_DIR: &str = "themes";
+
+    /// How the game looks: colors, font, sprites and background.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Theme {
+        /// Shown on the settings screen and saved to the config file.
+        pub name: String,
+        pub palette: Palette,
+        pub font: String,
+        /// Sprite atlas for the snake and fruit. Without one they are drawn
+        /// as squares in the palette's colors.
+        pub atlas: Option<String>,
+        /// Image drawn over the board behind everything else.
+        pub background: Option<String>,
+    }
+
+    /// Colors as `(red, green, blue)`, each between 0 and 1.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    #[serde(default)]
+    pub struct Palette {
+        pub background: [f32; 3],
+        pub wall: [f32; 3],
+        pub snake: [f32; 3],
+        pub tail: [f32; 3],
+        pub fruit: [f32; 3],
+        pub text: [f32; 3],
+    }
+
+    /// Resource: every theme found, and which one is in use.
+    pub struct Themes {
+        pub themes: Vec<Theme>,
+        pub current: usize,
+        /// Colors drawn with in place of any theme's, from
+        /// `DisplayConfig::palette`.
+        pub palette: Option<Palette>,
+    }
+
+    /// Palettes that replace the theme's for players who find some colors
+    /// hard to tell apart. Any of them but `Theme` also sets the theme's
+    /// sprite atlas aside, as its colors are baked in.
+    #[derive(Copy, Clone, Debug, PartialEq, Eq, Serialize, Deserialize)]
+    pub enum ColorVision {
+        /// The theme's own palette.
+        Theme,
+        Deuteranopia,
+        Protanopia,
+        Tritanopia,
+        HighContrast,
+    }
+
+    /// Sent when the settings screen changes the theme, palette or tail
+    /// pattern.
+    pub struct EventThemeChanged {
+    }
+}
+
+pub mod theme_functions {
+    use std::env;
+    use std::fs;
+    use std::path::{Path, PathBuf};
+    use super::theme_data::*;
+
+    /// The `assets` directory Bevy's `AssetServer` loads from: beside the
+    /// manifest when run through cargo, otherwise beside the executable.
+    pub fn asset_root() -> PathBuf {
+        let base = match env::var("CARGO_MANIFEST_DIR") {
+            Ok(dir) => PathBuf::from(dir),
+            Err(_) => env::current_exe()
+                .ok()
+                .and_then(|exe| exe.parent().map(Path::to_path_buf))
+                .unwrap_or_default(),
+        };
+        base.join("assets")
+    }
+
+    pub fn themes_dir() -> PathBuf {
+        asset_root().join(THEMES_DIR)
+    }
+
+    impl Default for Theme {
+        fn default() -> Self {
+            Theme {
+                name: "Classic".to_string(),
+                palette: Palette::default(),
+                font: "fonts/Pixeboy.ttf".to_string(),
+                atlas: None,
+                background: None,
+            }
+        }
+    }
+
+    impl Default for Palette {
+        fn default() -> Self {
+            Palette {
+                background: [0.0, 0.0, 0.0],
+                wall: [0.8, 0.8, 0.8],
+                snake: [0.0, 1.0, 0.0],
+                tail: [0.0, 1.0, 0.0],
+                fruit: [1.0, 0.0, 0.0],
+                text: [1.0, 1.0, 1.0],
+            }
+        }
+    }
+
+    impl Theme {
+        /// Reads and validates the theme at `path`.
+        pub fn load(path: &Path) -> Result<Theme, String> {
+            let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+            let theme: Theme = ron::de::from_str(&text)
+                .map_err(|err| format!("invalid theme `{}`: {}", path.display(), err))?;
+            theme
+                .validate()
+                .map_err(|err| format!("invalid theme `{}`: {}", path.display(), err))?;
+            Ok(theme)
+        }
+
+        /// Checks that the theme has a name and its colors are in range.
+        pub fn validate(&self) -> Result<(), String> {
+            let mut problems = Vec::new();
+            if self.name.trim().is_empty() {
+                problems.push("`name` must not be empty".to_string());
+            }
+            problems.extend(self.palette.problems("palette"));
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(problems.join("; "))
+            }
+        }
+    }
+
+    impl Palette {
+        /// Colors with a channel outside 0 to 1, described as fields of
+        /// `field`.
+        pub fn problems(&self, field: &str) -> Vec<String> {
+            let mut problems = Vec::new();
+            for (name, color) in [
+                ("background", self.background),
+                ("wall", self.wall),
+                ("snake", self.snake),
+                ("tail", self.tail),
+                ("fruit", self.fruit),
+                ("text", self.text),
+            ]
+            .iter()
+            {
+                if color.iter().any(|channel| *channel < 0.0 || *channel > 1.0) {
+                    problems.push(format!("`{}.{}` channels must be between 0 and 1, got {:?}", field, name, color));
+                }
+            }
+            problems
+        }
+    }
+
+    impl Themes {
+        /// Reads every theme in `dir`, in file name order, and picks the one
+        /// called `selected`. Themes that fail to load are reported and left
+        /// out; with none left the built-in flat theme is used.
+        pub fn load(dir: &Path, selected: &str) -> Themes {
+            let mut paths: Vec<_> = match fs::read_dir(dir) {
+                Ok(entries) => entries
+                    .filter_map(|entry| entry.ok().map(|entry| entry.path()))
+                    .filter(|path| path.extension().and_then(|ext| ext.to_str()) == Some("ron"))
+                    .collect(),
+                Err(_) => Vec::new(),
+            };
+            paths.sort();
+            let mut themes = Vec::new();
+            for path in paths {
+                match Theme::load(&path) {
+                    Ok(theme) => themes.push(theme),
+                    Err(err) => println!("Skipping theme: {}", err),
+                }
+            }
+            if themes.is_empty() {
+                themes.push(Theme::default());
+            }
+            let mut loaded = Themes { themes, current: 0, palette: None };
+            if !loaded.select(selected) {
+                println!("Unknown theme `{}`, using `{}`", selected, loaded.current().name);
+            }
+            loaded
+        }
+
+        pub fn current(&self) -> &Theme {
+            &self.themes[self.current]
+        }
+
+        /// Switches to the theme called `name`, ignoring case. Returns false
+        /// if there is no such theme.
+        pub fn select(&mut self, name: &str) -> bool {
+            match self.themes.iter().position(|theme| theme.name.eq_ignore_ascii_case(name)) {
+                Some(index) => {
+                    self.current = index;
+                    true
+                }
+                None => false,
+            }
+        }
+
+        /// The current theme as drawn with `vision`'s palette, or else with
+        /// the palette set in place of the theme's.
+        pub fn look(&self, vision: ColorVision) -> Theme {
+            let mut theme = self.current().clone();
+            if let Some(palette) = vision.palette().or_else(|| self.palette.clone()) {
+                theme.palette = palette;
+                theme.atlas = None;
+            }
+            theme
+        }
+
+        /// Moves `step` themes along the list, wrapping at either end.
+        pub fn cycle(&mut self, step: i32) {
+            let count = self.themes.len() as i32;
+            self.current = (self.current as i32 + step).rem_euclid(count) as usize;
+        }
+    }
+
+    impl Default for ColorVision {
+        fn default() -> Self {
+            ColorVision::Theme
+        }
+    }
+
+    impl ColorVision {
+        pub const ALL: [ColorVision; 5] = [
+            ColorVision::Theme,
+            ColorVision::Deuteranopia,
+            ColorVision::Protanopia,
+            ColorVision::Tritanopia,
+            ColorVision::HighContrast,
+        ];
+
+        pub fn label(self) -> &'static str {
+            match self {
+                ColorVision::Theme => "Theme",
+                ColorVision::Deuteranopia => "Deuteranopia",
+                ColorVision::Protanopia => "Protanopia",
+                ColorVision::Tritanopia => "Tritanopia",
+                ColorVision::HighContrast => "High contrast",
+            }
+        }
+
+        /// The palette to draw with instead of the theme's, if any. The
+        /// color-blind palettes keep the snake and fruit on either side of
+        /// the blue/orange or red/teal axis the player still sees.
+        /// Deuteranopia and protanopia both lose red against green, so they
+        /// share the blue snake; only the fruit differs, as protanopes see
+        /// orange as dark against the black board and get yellow instead.
+        pub fn palette(self) -> Option<Palette> {
+            let base = Palette {
+                background: [0.0, 0.0, 0.0],
+                wall: [0.6, 0.6, 0.6],
+                text: [1.0, 1.0, 1.0],
+                ..Palette::default()
+            };
+            let red_green = Palette {
+                snake: [0.0, 0.45, 0.7],
+                tail: [0.34, 0.71, 0.91],
+                ..base
+            };
+            match self {
+                ColorVision::Theme => None,
+                ColorVision::Deuteranopia => Some(Palette {
+                    fruit: [0.9, 0.62, 0.0],
+                    ..red_green
+                }),
+                ColorVision::Protanopia => Some(Palette {
+                    fruit: [0.94, 0.89, 0.26],
+                    ..red_green
+                }),
+                ColorVision::Tritanopia => Some(Palette {
+                    snake: [0.0, 0.6, 0.6],
+                    tail: [0.4, 0.8, 0.8],
+                    fruit: [0.86, 0.15, 0.15],
+                    ..base
+                }),
+                ColorVision::HighContrast => Some(Palette {
+                    wall: [1.0, 1.0, 1.0],
+                    snake: [1.0, 1.0, 0.0],
+                    tail: [1.0, 1.0, 1.0],
+                    fruit: [1.0, 0.0, 1.0],
+                    ..base
+                }),
+            }
+        }
+    }
+}
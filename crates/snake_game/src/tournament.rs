@@ -0,0 +1,392 @@
+pub mod tournament_data {
+    #[derive(Copy, Clone, Debug, PartialEq)]
+    pub enum Bracket {
+        /// Every group of `snakes_per_match` controllers meets once per seed.
+        RoundRobin,
+        /// Controllers with similar records are grouped together each round.
+        Swiss { rounds: usize },
+    }
+
+    pub struct TournamentConfig {
+        pub bracket: Bracket,
+        /// Every pairing is played once on each of these seeds.
+        pub seeds: Vec<u64>,
+        pub snakes_per_match: usize,
+        pub max_ticks: u32,
+        pub play_area: f32,
+        pub cell_size: f64,
+        /// Elo K-factor for a two-snake match.
+        pub k_factor: f64,
+    }
+
+    impl Default for TournamentConfig {
+        fn default() -> Self {
+            TournamentConfig {
+                bracket: Bracket::RoundRobin,
+                seeds: vec![1, 2, 3],
+                snakes_per_match: 2,
+                max_ticks: 2000,
+                play_area: 600.0,
+                cell_size: 25.0,
+                k_factor: 32.0,
+            }
+        }
+    }
+
+    #[derive(Clone, Debug)]
+    pub struct Standing {
+        pub name: String,
+        pub matches: usize,
+        pub wins: usize,
+        pub draws: usize,
+        pub losses: usize,
+        pub total_length: usize,
+        pub total_ticks: u64,
+        pub elo: f64,
+    }
+}
+
+pub mod tournament_functions {
+    use std::cmp::Ordering;
+    use super::tournament_data::*;
+    use crate::controller::controller_data::*;
+    use crate::sim::sim_data::*;
+
+    impl Standing {
+        pub fn new(name: String) -> Standing {
+            Standing {
+                name,
+                matches: 0,
+                wins: 0,
+                draws: 0,
+                losses: 0,
+                total_length: 0,
+                total_ticks: 0,
+                elo: 1500.0,
+            }
+        }
+
+        pub fn average_length(&self) -> f64 {
+            if self.matches == 0 { 0.0 } else { self.total_length as f64 / self.matches as f64 }
+        }
+
+        pub fn average_ticks(&self) -> f64 {
+            if self.matches == 0 { 0.0 } else { self.total_ticks as f64 / self.matches as f64 }
+        }
+
+        /// Tournament points: a win is worth one, a shared first place half.
+        pub fn points(&self) -> f64 {
+            self.wins as f64 + self.draws as f64 * 0.5
+        }
+    }
+
+    /// Plays one game between the given controllers and returns how each of
+    /// them placed.
+    pub fn play_match(
+        controllers: &mut [&mut Box<dyn Controller>],
+        config: &TournamentConfig,
+        seed: u64,
+    ) -> Vec<SimResult> {
+        let mut sim = Simulation::new(config.play_area, config.cell_size, controllers.len(), seed, config.max_ticks);
+        for controller in controllers.iter_mut() {
+            controller.reset();
+        }
+        while !sim.is_finished() {
+            let moves: Vec<_> = controllers
+                .iter_mut()
+                .enumerate()
+                .map(|(i, controller)| {
+                    let view = BoardView::from_sim(&sim, i);
+                    controller.next_move(&view)
+                })
+                .collect();
+            sim.step(&moves);
+        }
+        sim.results()
+    }
+
+    /// Runs every match of the tournament and returns the standings, best
+    /// first.
+    pub fn run_tournament(controllers: &mut [Box<dyn Controller>], config: &TournamentConfig) -> Vec<Standing> {
+        let mut standings: Vec<Standing> = controllers
+            .iter()
+            .map(|controller| Standing::new(controller.name()))
+            .collect();
+        let group_size = config.snakes_per_match.max(2).min(controllers.len());
+        if group_size < 2 {
+            return standings;
+        }
+
+        match config.bracket {
+            Bracket::RoundRobin => {
+                for group in combinations(controllers.len(), group_size) {
+                    for seed in config.seeds.iter() {
+                        run_group(controllers, &mut standings, &group, config, *seed);
+                    }
+                }
+            }
+            Bracket::Swiss { rounds } => {
+                for round in 0..rounds {
+                    for group in swiss_groups(&standings, group_size) {
+                        for seed in config.seeds.iter() {
+                            let seed = seed.wrapping_add(round as u64 * 1_000_003);
+                            run_group(controllers, &mut standings, &group, config, seed);
+                        }
+                    }
+                }
+            }
+        }
+
+        sort_standings(&mut standings);
+        standings
+    }
+
+    fn run_group(
+        controllers: &mut [Box<dyn Controller>],
+        standings: &mut [Standing],
+        group: &[usize],
+        config: &TournamentConfig,
+        seed: u64,
+    ) {
+        // Players take the snakes in group order, which is how
+        // `record_match` reads the results back.
+        let mut seats: Vec<Option<&mut Box<dyn Controller>>> = controllers.iter_mut().map(Some).collect();
+        let mut players: Vec<&mut Box<dyn Controller>> = group
+            .iter()
+            .map(|player| seats[*player].take().expect("a controller can only play once per match"))
+            .collect();
+        let results = play_match(&mut players, config, seed);
+        record_match(standings, group, &results, config.k_factor);
+    }
+
+    /// Adds a finished match to the standings. Elo is updated pairwise, as
+    /// if every pair of snakes in the match had played each other.
+    pub fn record_match(standings: &mut [Standing], group: &[usize], results: &[SimResult], k_factor: f64) {
+        let winners = results.iter().filter(|result| result.place == 0).count();
+        for (player, result) in group.iter().zip(results.iter()) {
+            let standing = &mut standings[*player];
+            standing.matches += 1;
+            standing.total_length += result.length;
+            standing.total_ticks += result.ticks_survived as u64;
+            if result.place == 0 && winners == 1 {
+                standing.wins += 1;
+            } else if result.place == 0 {
+                standing.draws += 1;
+            } else {
+                standing.losses += 1;
+            }
+        }
+
+        let k = k_factor / (group.len() - 1) as f64;
+        let ratings: Vec<f64> = group.iter().map(|player| standings[*player].elo).collect();
+        for (a, result_a) in results.iter().enumerate() {
+            let mut delta = 0.0;
+            for (b, result_b) in results.iter().enumerate() {
+                if a == b {
+                    continue;
+                }
+                let expected = 1.0 / (1.0 + 10f64.powf((ratings[b] - ratings[a]) / 400.0));
+                let actual = if result_a.place < result_b.place {
+                    1.0
+                } else if result_a.place == result_b.place {
+                    0.5
+                } else {
+                    0.0
+                };
+                delta += k * (actual - expected);
+            }
+            standings[group[a]].elo += delta;
+        }
+    }
+
+    /// Every way of picking `size` players out of `count`, in order.
+    pub fn combinations(count: usize, size: usize) -> Vec<Vec<usize>> {
+        let mut groups = Vec::new();
+        let mut current: Vec<usize> = (0..size).collect();
+        if size == 0 || size > count {
+            return groups;
+        }
+        loop {
+            groups.push(current.clone());
+            let mut i = size;
+            while i > 0 && current[i - 1] == count - size + i - 1 {
+                i -= 1;
+            }
+            if i == 0 {
+                return groups;
+            }
+            current[i - 1] += 1;
+            for j in i..size {
+                current[j] = current[j - 1] + 1;
+            }
+        }
+    }
+
+    /// Groups players with similar points (Elo breaking ties) for the next
+    /// Swiss round. When the field does not divide evenly, a player left on
+    /// their own joins the group before them.
+    pub fn swiss_groups(standings: &[Standing], size: usize) -> Vec<Vec<usize>> {
+        let mut order: Vec<usize> = (0..standings.len()).collect();
+        order.sort_by(|a, b| {
+            let (a, b) = (&standings[*a], &standings[*b]);
+            descending(a.points(), b.points()).then(descending(a.elo, b.elo))
+        });
+        let mut groups: Vec<Vec<usize>> = order.chunks(size.max(2)).map(|chunk| chunk.to_vec()).collect();
+        if groups.last().map(|last| last.len()) == Some(1) {
+            let leftover = groups.pop().unwrap_or_default();
+            if let Some(previous) = groups.last_mut() {
+                previous.extend(leftover);
+            }
+        }
+        groups
+    }
+
+    pub fn sort_standings(standings: &mut [Standing]) {
+        standings.sort_by(|a, b| descending(a.elo, b.elo).then(descending(a.points(), b.points())));
+    }
+
+    /// Orders `a` before `b` when it is larger. A NaN counts as equal to
+    /// anything rather than panicking.
+    fn descending(a: f64, b: f64) -> Ordering {
+        b.partial_cmp(&a).unwrap_or(Ordering::Equal)
+    }
+
+    /// The standings as CSV, one row per controller.
+    pub fn results_csv(standings: &[Standing]) -> String {
+        let mut csv = String::from("rank,controller,matches,wins,draws,losses,avg_length,avg_survival_ticks,elo\n");
+        for (rank, standing) in standings.iter().enumerate() {
+            csv += &format!(
+                "{},\"{}\",{},{},{},{},{:.2},{:.1},{:.0}\n",
+                rank + 1,
+                standing.name.replace('"', "\"\""),
+                standing.matches,
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.average_length(),
+                standing.average_ticks(),
+                standing.elo,
+            );
+        }
+        csv
+    }
+
+    /// The standings as an aligned plain-text table.
+    pub fn results_table(standings: &[Standing]) -> String {
+        let name_width = standings
+            .iter()
+            .map(|standing| standing.name.len())
+            .max()
+            .unwrap_or(0)
+            .max("controller".len());
+        let mut table = format!(
+            "{:>4}  {:<width$}  {:>7}  {:>5}  {:>5}  {:>6}  {:>10}  {:>12}  {:>6}\n",
+            "rank", "controller", "matches", "wins", "draws", "losses", "avg length", "avg survival", "elo",
+            width = name_width,
+        );
+        for (rank, standing) in standings.iter().enumerate() {
+            table += &format!(
+                "{:>4}  {:<width$}  {:>7}  {:>5}  {:>5}  {:>6}  {:>10.2}  {:>12.1}  {:>6.0}\n",
+                rank + 1,
+                standing.name,
+                standing.matches,
+                standing.wins,
+                standing.draws,
+                standing.losses,
+                standing.average_length(),
+                standing.average_ticks(),
+                standing.elo,
+                width = name_width,
+            );
+        }
+        table
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+        use crate::snake::snake_data::*;
+
+        /// Heads straight down into the wall.
+        struct Crasher;
+
+        impl Controller for Crasher {
+            fn name(&self) -> String {
+                "crasher".to_string()
+            }
+
+            fn next_move(&mut self, _view: &BoardView) -> SnakeDirection {
+                SnakeDirection::DOWN
+            }
+        }
+
+        fn result(place: usize, length: usize) -> SimResult {
+            SimResult { length, score: length - 1, ticks_survived: 10, alive: place == 0, place }
+        }
+
+        fn standings(count: usize) -> Vec<Standing> {
+            (0..count).map(|i| Standing::new(format!("player {}", i))).collect()
+        }
+
+        #[test]
+        fn run_group_credits_players_in_group_order() {
+            let mut controllers: Vec<Box<dyn Controller>> = vec![Box::new(Crasher), Box::new(GreedyController)];
+            let mut table = standings(2);
+            let config = TournamentConfig::default();
+            run_group(&mut controllers, &mut table, &[1, 0], &config, 7);
+            assert_eq!(table[1].wins, 1, "greedy should win");
+            assert_eq!(table[0].losses, 1, "the crasher should lose");
+            assert!(table[1].elo > table[0].elo);
+        }
+
+        #[test]
+        fn record_match_credits_results_to_the_group() {
+            let mut table = standings(3);
+            record_match(&mut table, &[2, 0], &[result(0, 5), result(1, 2)], 32.0);
+            assert_eq!((table[2].wins, table[2].total_length), (1, 5));
+            assert_eq!((table[0].losses, table[0].total_length), (1, 2));
+            assert_eq!(table[1].matches, 0);
+            assert!((table[2].elo - 1516.0).abs() < 1e-9);
+            assert!((table[0].elo - 1484.0).abs() < 1e-9);
+        }
+
+        #[test]
+        fn record_match_shares_first_place_as_draws() {
+            let mut table = standings(2);
+            record_match(&mut table, &[0, 1], &[result(0, 3), result(0, 3)], 32.0);
+            assert_eq!((table[0].draws, table[1].draws), (1, 1));
+            assert!((table[0].elo - 1500.0).abs() < 1e-9);
+            assert!((table[1].elo - 1500.0).abs() < 1e-9);
+        }
+
+        #[test]
+        fn swiss_groups_pair_players_by_points() {
+            let mut table = standings(4);
+            table[3].wins = 2;
+            table[1].wins = 1;
+            table[0].elo = 1600.0;
+            assert_eq!(swiss_groups(&table, 2), vec![vec![3, 1], vec![0, 2]]);
+        }
+
+        #[test]
+        fn swiss_groups_merge_a_leftover_player() {
+            let mut table = standings(5);
+            table[4].wins = 1;
+            let groups = swiss_groups(&table, 2);
+            assert_eq!(groups.len(), 2);
+            assert_eq!(groups[0][0], 4);
+            assert_eq!(groups[1].len(), 3);
+            assert_eq!(groups.iter().map(|group| group.len()).sum::<usize>(), 5);
+        }
+
+        #[test]
+        fn sorting_survives_a_nan_elo() {
+            let mut table = standings(3);
+            table[1].elo = f64::NAN;
+            table[2].elo = 1700.0;
+            sort_standings(&mut table);
+            assert_eq!(table.len(), 3);
+            swiss_groups(&table, 2);
+        }
+    }
+}
@@ -0,0 +1,489 @@
+pub mod training_data {
+    use serde::{Deserialize, Serialize};
+
+    /// Number of weights in a `Genome`, one per feature scored by
+    /// `GenomeController` for each candidate move.
+    pub const GENOME_FEATURES: usize = 6;
+
+    /// Fewest genomes a generation can have: breeding needs two parents.
+    pub const MIN_POPULATION: usize = 2;
+
+    /// Weights of a heuristic controller. Each candidate move is described
+    /// by a set of features (will it die, does it get closer to the fruit,
+    /// how much room is left, ...) and the move with the highest weighted
+    /// sum is taken.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Genome {
+        pub weights: Vec<f64>,
+    }
+
+    /// A `Genome` steering a snake.
+    pub struct GenomeController {
+        pub name: String,
+        pub genome: Genome,
+    }
+
+    /// How a game played by a genome is turned into a single fitness number.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct FitnessWeights {
+        pub per_fruit: f64,
+        pub per_tick: f64,
+        pub death_penalty: f64,
+    }
+
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct TrainingConfig {
+        pub population: usize,
+        pub generations: usize,
+        /// Chance that each weight of a child is mutated.
+        pub mutation_rate: f64,
+        /// Largest change a single mutation makes to a weight.
+        pub mutation_strength: f64,
+        /// Best genomes copied unchanged into the next generation.
+        pub elite: usize,
+        pub games_per_genome: usize,
+        pub max_ticks: u32,
+        /// A game is stopped if no fruit is eaten for this many ticks.
+        pub starvation_ticks: u32,
+        pub play_area: f32,
+        pub cell_size: f64,
+        pub fitness: FitnessWeights,
+        pub seed: u64,
+    }
+
+    /// The state of a training run, written to disk so it can be resumed.
+    #[derive(Clone, Debug, Serialize, Deserialize)]
+    pub struct Checkpoint {
+        pub generation: usize,
+        pub config: TrainingConfig,
+        pub population: Vec<Genome>,
+        pub best: Genome,
+        pub best_fitness: f64,
+        /// Seed of the random numbers for the next generation, so a resumed
+        /// run breeds the same genomes as one that never stopped.
+        /// Checkpoints written without it resume from `config.seed`.
+        #[serde(default)]
+        pub rng_seed: Option<u64>,
+    }
+}
+
+pub mod training_functions {
+    use bevy::prelude::*;
+    use rand::{Rng, SeedableRng, rngs::StdRng};
+    use std::cmp::Ordering;
+    use std::collections::VecDeque;
+    use std::fs;
+    use std::path::Path;
+    use super::training_data::*;
+    use crate::controller::controller_data::*;
+    use crate::sim::sim_data::*;
+    use crate::snake::snake_data::*;
+
+    impl Default for FitnessWeights {
+        fn default() -> Self {
+            FitnessWeights {
+                per_fruit: 100.0,
+                per_tick: 0.1,
+                death_penalty: 50.0,
+            }
+        }
+    }
+
+    impl Default for TrainingConfig {
+        fn default() -> Self {
+            TrainingConfig {
+                population: 50,
+                generations: 100,
+                mutation_rate: 0.2,
+                mutation_strength: 0.5,
+                elite: 5,
+                games_per_genome: 3,
+                max_ticks: 2000,
+                starvation_ticks: 300,
+                play_area: 600.0,
+                cell_size: 25.0,
+                fitness: FitnessWeights::default(),
+                seed: 1,
+            }
+        }
+    }
+
+    impl TrainingConfig {
+        /// Checks that there are enough genomes to breed, games to score
+        /// them and sensible mutation and board settings, and lists each
+        /// problem.
+        pub fn validate(&self) -> Result<(), String> {
+            let mut problems = Vec::new();
+            if self.population < MIN_POPULATION {
+                problems.push(format!("`population` must be at least {}, got {}", MIN_POPULATION, self.population));
+            }
+            if self.games_per_genome == 0 {
+                problems.push("`games_per_genome` must be at least 1".to_string());
+            }
+            if !(0.0..=1.0).contains(&self.mutation_rate) {
+                problems.push(format!("`mutation_rate` must be between 0 and 1, got {}", self.mutation_rate));
+            }
+            if self.mutation_strength <= 0.0 {
+                problems.push(format!("`mutation_strength` must be positive, got {}", self.mutation_strength));
+            }
+            if self.cell_size <= 0.0 {
+                problems.push(format!("`cell_size` must be positive, got {}", self.cell_size));
+            }
+            if self.play_area <= 0.0 {
+                problems.push(format!("`play_area` must be positive, got {}", self.play_area));
+            }
+            if problems.is_empty() {
+                Ok(())
+            } else {
+                Err(problems.join("; "))
+            }
+        }
+    }
+
+    impl Genome {
+        pub fn random(rng: &mut impl Rng) -> Genome {
+            Genome {
+                weights: (0..GENOME_FEATURES).map(|_| rng.gen_range(-1.0, 1.0)).collect(),
+            }
+        }
+
+        /// Takes each weight from either parent at random.
+        pub fn crossover(&self, other: &Genome, rng: &mut impl Rng) -> Genome {
+            Genome {
+                weights: self
+                    .weights
+                    .iter()
+                    .zip(other.weights.iter())
+                    .map(|(a, b)| if rng.gen::<bool>() { *a } else { *b })
+                    .collect(),
+            }
+        }
+
+        pub fn mutate(&mut self, rate: f64, strength: f64, rng: &mut impl Rng) {
+            for weight in self.weights.iter_mut() {
+                if rng.gen::<f64>() < rate {
+                    *weight += rng.gen_range(-strength, strength);
+                }
+            }
+        }
+    }
+
+    impl Controller for GenomeController {
+        fn name(&self) -> String {
+            self.name.clone()
+        }
+
+        fn next_move(&mut self, view: &BoardView) -> SnakeDirection {
+            let mut best = view.direction;
+            let mut best_score = f64::NEG_INFINITY;
+            for direction in SnakeDirection::ALL.iter() {
+                if *direction == view.direction.opposite() {
+                    continue;
+                }
+                let score: f64 = move_features(view, *direction)
+                    .iter()
+                    .zip(self.genome.weights.iter())
+                    .map(|(feature, weight)| feature * weight)
+                    .sum();
+                if score > best_score {
+                    best_score = score;
+                    best = *direction;
+                }
+            }
+            best
+        }
+    }
+
+    /// Features of moving in `direction`, in the order the genome weights
+    /// them: dies next tick, closer to the fruit, share of the board still
+    /// reachable, keeps going straight, blocked neighbours of the new cell,
+    /// distance of the new cell from the walls.
+    pub fn move_features(view: &BoardView, direction: SnakeDirection) -> [f64; GENOME_FEATURES] {
+        let next = direction.step(view.head);
+        let blocked = view.is_blocked(next);
+        let closer = match view.fruit {
+            Some(fruit) => {
+                let before = (view.head.x() - fruit.x()).abs() + (view.head.y() - fruit.y()).abs();
+                let after = (next.x() - fruit.x()).abs() + (next.y() - fruit.y()).abs();
+                (before - after) as f64
+            }
+            None => 0.0,
+        };
+        let room = if blocked { 0.0 } else { reachable_share(view, next) };
+        let neighbours = SnakeDirection::ALL
+            .iter()
+            .filter(|around| view.is_blocked(around.step(next)))
+            .count();
+        let wall_distance = (view.grid_max - next.x().abs()).min(view.grid_max - next.y().abs());
+        [
+            if blocked { 1.0 } else { 0.0 },
+            closer,
+            room,
+            if direction == view.direction { 1.0 } else { 0.0 },
+            neighbours as f64 / 4.0,
+            (wall_distance / view.grid_max) as f64,
+        ]
+    }
+
+    /// Share of the board's free cells reachable from `start`.
+    fn reachable_share(view: &BoardView, start: Vec2) -> f64 {
+        let side = (view.grid_max * 2.0 - 1.0) as usize;
+        let index = |cell: Vec2| {
+            (cell.x() + view.grid_max - 1.0) as usize * side + (cell.y() + view.grid_max - 1.0) as usize
+        };
+        let mut seen = vec![false; side * side];
+        let mut queue = VecDeque::new();
+        seen[index(start)] = true;
+        queue.push_back(start);
+        let mut reached = 0;
+        while let Some(cell) = queue.pop_front() {
+            reached += 1;
+            for direction in SnakeDirection::ALL.iter() {
+                let next = direction.step(cell);
+                if view.is_blocked(next) || seen[index(next)] {
+                    continue;
+                }
+                seen[index(next)] = true;
+                queue.push_back(next);
+            }
+        }
+        reached as f64 / (side * side) as f64
+    }
+
+    /// Average fitness of `genome` over the configured number of games.
+    /// Every genome of a generation plays the same seeds.
+    pub fn evaluate(genome: &Genome, config: &TrainingConfig, generation: usize) -> f64 {
+        let mut controller = GenomeController { name: String::new(), genome: genome.clone() };
+        let mut total = 0.0;
+        for game in 0..config.games_per_genome {
+            let seed = config
+                .seed
+                .wrapping_mul(1_000_003)
+                .wrapping_add((generation * config.games_per_genome + game) as u64);
+            let mut sim = Simulation::new(config.play_area, config.cell_size, 1, seed, config.max_ticks);
+            let mut last_score = 0;
+            let mut hungry = 0;
+            while !sim.is_finished() && hungry < config.starvation_ticks {
+                let view = BoardView::from_sim(&sim, 0);
+                let next_move = controller.next_move(&view);
+                sim.step(&[next_move]);
+                if sim.snakes[0].score > last_score {
+                    last_score = sim.snakes[0].score;
+                    hungry = 0;
+                } else {
+                    hungry += 1;
+                }
+            }
+            let snake = &sim.snakes[0];
+            total += config.fitness.per_fruit * snake.score as f64
+                + config.fitness.per_tick * snake.ticks_survived as f64
+                - if snake.alive { 0.0 } else { config.fitness.death_penalty };
+        }
+        total / config.games_per_genome.max(1) as f64
+    }
+
+    /// Picks the fittest of three random genomes.
+    fn select<'a>(ranked: &'a [(Genome, f64)], rng: &mut impl Rng) -> &'a Genome {
+        let mut best = &ranked[rng.gen_range(0, ranked.len())];
+        for _ in 0..2 {
+            let other = &ranked[rng.gen_range(0, ranked.len())];
+            if other.1 > best.1 {
+                best = other;
+            }
+        }
+        &best.0
+    }
+
+    /// Builds the next generation from a population ranked best first.
+    pub fn next_generation(ranked: &[(Genome, f64)], config: &TrainingConfig, rng: &mut impl Rng) -> Vec<Genome> {
+        let mut population: Vec<Genome> = ranked
+            .iter()
+            .take(config.elite.min(config.population))
+            .map(|(genome, _)| genome.clone())
+            .collect();
+        while population.len() < config.population {
+            let mut child = select(ranked, rng).crossover(select(ranked, rng), rng);
+            child.mutate(config.mutation_rate, config.mutation_strength, rng);
+            population.push(child);
+        }
+        population
+    }
+
+    /// Runs training from `start` (a fresh random population when `None`),
+    /// calling `on_generation` with each new checkpoint. A resumed run goes
+    /// on under `config`, so pass the checkpoint's own config to carry on
+    /// the run unchanged.
+    pub fn train(
+        config: &TrainingConfig,
+        start: Option<Checkpoint>,
+        mut on_generation: impl FnMut(&Checkpoint),
+    ) -> Result<Checkpoint, String> {
+        config.validate()?;
+        let mut checkpoint = match start {
+            Some(checkpoint) => {
+                if checkpoint.population.is_empty() {
+                    return Err("the checkpoint has no population to resume".to_string());
+                }
+                if let Some(genome) = checkpoint.population.iter().find(|genome| genome.weights.len() != GENOME_FEATURES) {
+                    return Err(format!(
+                        "the checkpoint has a genome with {} weights, expected {}",
+                        genome.weights.len(),
+                        GENOME_FEATURES
+                    ));
+                }
+                checkpoint
+            }
+            None => {
+                let mut rng = StdRng::seed_from_u64(config.seed);
+                let population: Vec<Genome> = (0..config.population).map(|_| Genome::random(&mut rng)).collect();
+                Checkpoint {
+                    generation: 0,
+                    config: config.clone(),
+                    best: population[0].clone(),
+                    population,
+                    best_fitness: f64::NEG_INFINITY,
+                    rng_seed: Some(rng.gen()),
+                }
+            }
+        };
+        checkpoint.config = config.clone();
+
+        while checkpoint.generation < config.generations {
+            let mut rng = StdRng::seed_from_u64(checkpoint.rng_seed.unwrap_or(config.seed));
+            let mut ranked: Vec<(Genome, f64)> = checkpoint
+                .population
+                .iter()
+                .map(|genome| (genome.clone(), evaluate(genome, config, checkpoint.generation)))
+                .collect();
+            ranked.sort_by(|a, b| b.1.partial_cmp(&a.1).unwrap_or(Ordering::Equal));
+            if ranked[0].1 > checkpoint.best_fitness {
+                checkpoint.best = ranked[0].0.clone();
+                checkpoint.best_fitness = ranked[0].1;
+            }
+            checkpoint.population = next_generation(&ranked, config, &mut rng);
+            checkpoint.rng_seed = Some(rng.gen());
+            checkpoint.generation += 1;
+            on_generation(&checkpoint);
+        }
+        Ok(checkpoint)
+    }
+
+    pub fn save_checkpoint(checkpoint: &Checkpoint, path: &Path) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(checkpoint, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("could not write `{}`: {}", path.display(), err))
+    }
+
+    pub fn load_checkpoint(path: &Path) -> Result<Checkpoint, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+        ron::de::from_str(&text).map_err(|err| format!("invalid checkpoint `{}`: {}", path.display(), err))
+    }
+
+    pub fn save_genome(genome: &Genome, path: &Path) -> Result<(), String> {
+        let text = ron::ser::to_string_pretty(genome, ron::ser::PrettyConfig::default())
+            .map_err(|err| err.to_string())?;
+        fs::write(path, text).map_err(|err| format!("could not write `{}`: {}", path.display(), err))
+    }
+
+    /// Loads a genome saved by `save_genome`, or the best genome of a
+    /// checkpoint.
+    pub fn load_genome(path: &Path) -> Result<Genome, String> {
+        let text = fs::read_to_string(path).map_err(|err| format!("could not read `{}`: {}", path.display(), err))?;
+        let genome = ron::de::from_str::<Genome>(&text)
+            .or_else(|_| ron::de::from_str::<Checkpoint>(&text).map(|checkpoint| checkpoint.best))
+            .map_err(|err| format!("invalid genome `{}`: {}", path.display(), err))?;
+        if genome.weights.len() != GENOME_FEATURES {
+            return Err(format!(
+                "genome `{}` has {} weights, expected {}",
+                path.display(),
+                genome.weights.len(),
+                GENOME_FEATURES
+            ));
+        }
+        Ok(genome)
+    }
+
+    #[cfg(test)]
+    mod tests {
+        use super::*;
+
+        fn small_config() -> TrainingConfig {
+            TrainingConfig {
+                population: 4,
+                generations: 3,
+                elite: 1,
+                games_per_genome: 1,
+                max_ticks: 50,
+                starvation_ticks: 20,
+                play_area: 250.0,
+                ..TrainingConfig::default()
+            }
+        }
+
+        #[test]
+        fn an_empty_population_is_an_error() {
+            for population in 0..MIN_POPULATION {
+                let config = TrainingConfig { population, ..small_config() };
+                assert!(train(&config, None, |_| {}).is_err());
+            }
+
+            let mut checkpoint = train(&TrainingConfig { generations: 0, ..small_config() }, None, |_| {}).unwrap();
+            checkpoint.population.clear();
+            assert!(train(&small_config(), Some(checkpoint), |_| {}).is_err());
+        }
+
+        #[test]
+        fn a_resumed_run_matches_an_uninterrupted_one() {
+            let config = small_config();
+            let whole = train(&config, None, |_| {}).unwrap();
+
+            let mut saved = None;
+            train(&config, None, |checkpoint| {
+                if checkpoint.generation == 1 {
+                    saved = Some(checkpoint.clone());
+                }
+            })
+            .unwrap();
+            let resumed = train(&config, saved, |_| {}).unwrap();
+
+            assert_eq!(resumed.generation, whole.generation);
+            assert_eq!(resumed.best.weights, whole.best.weights);
+            let weights = |checkpoint: &Checkpoint| -> Vec<Vec<f64>> {
+                checkpoint.population.iter().map(|genome| genome.weights.clone()).collect()
+            };
+            assert_eq!(weights(&resumed), weights(&whole));
+        }
+
+        #[test]
+        fn a_saved_checkpoint_resumes_with_its_own_config() {
+            let config = TrainingConfig {
+                population: 6,
+                elite: 2,
+                mutation_rate: 0.5,
+                mutation_strength: 0.25,
+                fitness: FitnessWeights { per_fruit: 10.0, per_tick: 1.0, death_penalty: 5.0 },
+                seed: u64::MAX,
+                ..small_config()
+            };
+            let whole = train(&config, None, |_| {}).unwrap();
+
+            let path = std::env::temp_dir().join(format!("snake_checkpoint_{}.ron", std::process::id()));
+            train(&config, None, |checkpoint| {
+                if checkpoint.generation == 2 {
+                    save_checkpoint(checkpoint, &path).unwrap();
+                }
+            })
+            .unwrap();
+            let saved = load_checkpoint(&path).unwrap();
+            let _ = fs::remove_file(&path);
+            let saved_config = saved.config.clone();
+            assert_eq!(saved_config.population, 6);
+            assert_eq!(saved_config.seed, u64::MAX);
+            let resumed = train(&saved_config, Some(saved), |_| {}).unwrap();
+
+            assert_eq!(resumed.population.len(), 6);
+            assert_eq!(resumed.best_fitness, whole.best_fitness);
+            assert_eq!(resumed.best.weights, whole.best.weights);
+        }
+    }
+}
@@ -1,11 +1,23 @@
 pub mod game_ui {
     use crate::game::game_data::*;
+    use crate::scores::scores_data::*;
+    use crate::state::state_data::*;
+    use crate::bindings::bindings_data::*;
+    use crate::bindings::bindings_functions::*;
+    use crate::config::config_data::*;
+    use crate::config::config_functions::*;
+    use crate::theme::theme_data::*;
     use bevy::prelude::*;
 
     pub fn init_ui(
         mut commands: Commands,
-        asset_server: Res<AssetServer>
+        asset_server: Res<AssetServer>,
+        config: Res<GameConfig>,
+        themes: Res<Themes>,
     ) {
+        let theme = themes.look(config.display.color_vision);
+        let font: Handle<Font> = asset_server.load(theme.font.as_str());
+        let text_color = rgb(theme.palette.text);
         commands
             // texture
             .spawn(TextComponents {
@@ -20,10 +32,10 @@ pub mod game_ui {
                 },
                 text: Text {
                     value: "Score".to_string(),
-                    font: asset_server.load("fonts/Pixeboy.ttf"),
+                    font: font.clone(),
                     style: TextStyle {
                         font_size: 30.0,
-                        color: Color::WHITE,
+                        color: text_color,
                     },
                 },
                 ..Default::default()
@@ -43,35 +55,184 @@ pub mod game_ui {
                 },
                 text: Text {
                     value: "High Scores \n\n1.  0 \n\n2.  0 \n\n3.  0".to_string(),
-                    font: asset_server.load("fonts/Pixeboy.ttf"),
+                    font: font.clone(),
                     style: TextStyle {
                         font_size: 30.0,
-                        color: Color::WHITE,
+                        color: text_color,
                     },
                 },
                 ..Default::default()
             })
             .with(PrevScoreText);
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Percent(35.0),
+                        top: Val::Percent(35.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text {
+                    value: String::new(),
+                    font: font.clone(),
+                    style: TextStyle {
+                        font_size: 40.0,
+                        color: text_color,
+                    },
+                },
+                ..Default::default()
+            })
+            .with(InitialsText);
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Percent(15.0),
+                        top: Val::Percent(10.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text {
+                    value: String::new(),
+                    font: font.clone(),
+                    style: TextStyle {
+                        font_size: 26.0,
+                        color: text_color,
+                    },
+                },
+                ..Default::default()
+            })
+            .with(LeaderboardText);
+        commands
+            .spawn(TextComponents {
+                style: Style {
+                    position_type: PositionType::Absolute,
+                    position: Rect {
+                        left: Val::Percent(30.0),
+                        top: Val::Percent(15.0),
+                        ..Default::default()
+                    },
+                    ..Default::default()
+                },
+                text: Text {
+                    value: String::new(),
+                    font,
+                    style: TextStyle {
+                        font_size: 30.0,
+                        color: text_color,
+                    },
+                },
+                ..Default::default()
+            })
+            .with(ScreenText);
     }
 
     pub fn update_high_scores (
         game: Res<GameState>,
+        high_scores: Res<HighScores>,
         mut score_query: Query<(&mut Text, &PrevScoreText)>
     ) {
+        let leaderboard = high_scores.leaderboard(game.mode, game.difficulty);
         for (mut text, _) in score_query.iter_mut() {
             let mut score_board = String::from("High Scores \n");
-            let mut i = 1;
-            for score in game.prev_scores.iter() {
-                if i > 3 {
-                    break;
+            for rank in 0..3 {
+                match leaderboard.entries.get(rank) {
+                    Some(entry) => score_board += &format!("\n{}. {} {}\n", rank + 1, entry.initials, entry.score),
+                    None => score_board += &format!("\n{}. --- 0\n", rank + 1),
                 }
-                score_board += &format!("\n{}. {}\n", i, score);
-                i += 1;
-                // println!("{}", score);
             }
             text.value = score_board;
         }
     }
+
+    pub fn update_initials_text (
+        entry: Res<InitialsEntry>,
+        mut initials_query: Query<(&mut Text, &InitialsText)>
+    ) {
+        for (mut text, _) in initials_query.iter_mut() {
+            text.value = match &entry.pending {
+                Some(run) => {
+                    let letters: Vec<String> = entry
+                        .letters
+                        .iter()
+                        .enumerate()
+                        .map(|(i, letter)| {
+                            if i == entry.cursor {
+                                format!("[{}]", *letter as char)
+                            } else {
+                                format!(" {} ", *letter as char)
+                            }
+                        })
+                        .collect();
+                    format!("NEW HIGH SCORE! {}\n\nEnter your initials\n\n{}\n\nPress Enter", run.score, letters.join(""))
+                }
+                None => String::new(),
+            };
+        }
+    }
+
+    pub fn update_leaderboard_screen (
+        game: Res<GameState>,
+        high_scores: Res<HighScores>,
+        flow: Res<GameFlow>,
+        mut leaderboard_query: Query<(&mut Text, &LeaderboardText)>
+    ) {
+        for (mut text, _) in leaderboard_query.iter_mut() {
+            if !flow.is(AppState::Leaderboard) {
+                text.value = String::new();
+                continue;
+            }
+            let leaderboard = high_scores.leaderboard(game.mode, game.difficulty);
+            let mut board = format!(
+                "LEADERBOARD  {:?} / {:?}\n\n {:>2}  {:<4} {:>6} {:>5} {:>6}  {:<10}\n",
+                game.mode, game.difficulty, "#", "NAME", "SCORE", "LEN", "TIME", "DATE"
+            );
+            for (rank, entry) in leaderboard.entries.iter().enumerate() {
+                let seconds = entry.duration.round() as u32;
+                board += &format!(
+                    "\n {:>2}. {:<4} {:>6} {:>5} {:>3}:{:02}  {}\n",
+                    rank + 1,
+                    entry.initials,
+                    entry.score,
+                    entry.length,
+                    seconds / 60,
+                    seconds % 60,
+                    entry.achieved.format("%Y-%m-%d"),
+                );
+            }
+            if leaderboard.entries.is_empty() {
+                board += "\n No runs yet\n";
+            }
+            board += "\n\n Press Tab to close";
+            text.value = board;
+        }
+    }
+
+    /// Switches every text over to the new theme's font and color.
+    pub fn update_text_theme(
+        mut theme_reader: Local<EventReader<EventThemeChanged>>,
+        theme_events: Res<Events<EventThemeChanged>>,
+        config: Res<GameConfig>,
+        themes: Res<Themes>,
+        asset_server: Res<AssetServer>,
+        mut text_query: Query<&mut Text>,
+    ) {
+        if theme_reader.iter(&theme_events).last().is_none() {
+            return;
+        }
+        let theme = themes.look(config.display.color_vision);
+        let font: Handle<Font> = asset_server.load(theme.font.as_str());
+        for mut text in text_query.iter_mut() {
+            text.font = font.clone();
+            text.style.color = rgb(theme.palette.text);
+        }
+    }
+
     pub fn update_score (
         game: Res<GameState>,
         mut score_query: Query<(&mut Text, &ScoreText)>
@@ -80,4 +241,120 @@ pub mod game_ui {
             text.value = format!("Score: {}", game.score);
         }
     }
-}
\ No newline at end of file
+
+    /// Title, pause, game over, settings and controls screens.
+    pub fn update_screen_text (
+        game: Res<GameState>,
+        flow: Res<GameFlow>,
+        menu: Res<SettingsMenu>,
+        controls: Res<ControlsMenu>,
+        bindings: Res<KeyBindings>,
+        config: Res<GameConfig>,
+        themes: Res<Themes>,
+        pause_menu: Res<PauseMenu>,
+        entry: Res<InitialsEntry>,
+        last_round: Res<LastRound>,
+        mut screen_query: Query<(&mut Text, &ScreenText)>
+    ) {
+        for (mut text, _) in screen_query.iter_mut() {
+            text.value = match flow.current {
+                AppState::Title => "S N A K E\n\nEnter  Play\nTab  Leaderboard\nS  Settings".to_string(),
+                AppState::Paused => {
+                    let mut paused = String::from("PAUSED\n");
+                    for (i, item) in PauseItem::ALL.iter().enumerate() {
+                        let cursor = if i == pause_menu.cursor { ">" } else { " " };
+                        paused += &format!("\n{} {}", cursor, item.label());
+                    }
+                    paused
+                }
+                AppState::GameOver if entry.pending.is_none() => {
+                    let mut summary = String::from("GAME OVER\n");
+                    if let Some(round) = &last_round.0 {
+                        if round.record {
+                            summary += "\nNEW RECORD!\n";
+                        }
+                        summary += &format!(
+                            "\n{}\n\nScore  {}\nLength  {}\nTime  {}:{:02}",
+                            round.cause.describe(),
+                            round.score,
+                            round.length,
+                            round.duration as u32 / 60,
+                            round.duration as u32 % 60
+                        );
+                        let mut fruit: Vec<_> = round.fruit_eaten.iter().collect();
+                        fruit.sort_by_key(|(kind, _)| format!("{:?}", kind));
+                        for (kind, count) in fruit {
+                            summary += &format!("\n{:?}  {}", kind, count);
+                        }
+                    }
+                    summary += &format!(
+                        "\n\n{}  Retry\nTab  Leaderboard\nEsc  Title",
+                        key_list(&bindings, Action::Restart)
+                    );
+                    summary
+                }
+                AppState::Settings if controls.open => {
+                    let mut screen = String::from("CONTROLS\n");
+                    for (i, action) in Action::ALL.iter().enumerate() {
+                        let cursor = if i == controls.cursor { ">" } else { " " };
+                        let keys = if i == controls.cursor && controls.listening {
+                            "press a key...".to_string()
+                        } else {
+                            key_list(&bindings, *action)
+                        };
+                        screen += &format!("\n{} {}  {}", cursor, action.label(), keys);
+                    }
+                    if let Some(message) = &controls.message {
+                        screen += &format!("\n\n{}", message);
+                    }
+                    screen += "\n\nEnter  Add key\nBackspace  Clear\nR  Defaults\nEsc  Save";
+                    screen
+                }
+                AppState::Settings => {
+                    let mut settings = String::from("SETTINGS\n");
+                    for (i, item) in SettingsItem::ALL.iter().enumerate() {
+                        let value = match item {
+                            SettingsItem::Mode => format!("{:?}", game.mode),
+                            SettingsItem::Difficulty => format!("{:?}", game.difficulty),
+                            SettingsItem::Steering => bindings.steering.label().to_string(),
+                            SettingsItem::Smooth => on_off(config.display.smooth_movement).to_string(),
+                            SettingsItem::Theme => themes.current().name.clone(),
+                            SettingsItem::Palette => config.display.color_vision.label().to_string(),
+                            SettingsItem::TailPattern => on_off(config.display.tail_pattern).to_string(),
+                            SettingsItem::FruitBurst => on_off(config.display.effects.fruit_burst).to_string(),
+                            SettingsItem::DeathExplosion => on_off(config.display.effects.death_explosion).to_string(),
+                            SettingsItem::ScreenShake => on_off(config.display.effects.screen_shake).to_string(),
+                            SettingsItem::Flash => on_off(config.display.effects.flash).to_string(),
+                            SettingsItem::Controls => String::new(),
+                        };
+                        let cursor = if i == menu.cursor { ">" } else { " " };
+                        if value.is_empty() {
+                            settings += &format!("\n{} {}", cursor, item.label());
+                        } else if item.is_round_rule() && flow.is_paused() {
+                            settings += &format!("\n{} {}  {}  (locked while paused)", cursor, item.label(), value);
+                        } else {
+                            settings += &format!("\n{} {}  < {} >", cursor, item.label(), value);
+                        }
+                    }
+                    settings += "\n\nEsc  Back";
+                    settings
+                }
+                _ => String::new(),
+            };
+        }
+    }
+
+    /// Names of the keys bound to `action`, for on-screen hints.
+    fn key_list(bindings: &KeyBindings, action: Action) -> String {
+        let names: Vec<&str> = bindings.keys_for(action).iter().map(|key| key_name(*key)).collect();
+        if names.is_empty() {
+            "-".to_string()
+        } else {
+            names.join(" ")
+        }
+    }
+
+    fn on_off(value: bool) -> &'static str {
+        if value { "On" } else { "Off" }
+    }
+}
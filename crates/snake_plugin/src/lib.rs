@@ -8,6 +8,9 @@ mod tests {
 
 pub mod plugin {
     use bevy::prelude::*;
+    use bevy::window::WindowFocused;
+    use std::path::PathBuf;
+    use std::sync::Mutex;
     use snake_game:: {
         snake::snake_functions::*,
         snake::snake_data::*,
@@ -15,107 +18,400 @@ pub mod plugin {
         game::game_functions::*,
         fruit::fruit_logic::*,
         ui::game_ui::*,
+        render::game_render::*,
+        controller::controller_data::*,
+        controller::controller_functions::*,
+        dataset::dataset_data::*,
+        dataset::dataset_functions::*,
+        scores::scores_data::*,
+        scores::scores_functions::*,
+        state::state_data::*,
+        state::state_functions::*,
+        config::config_data::*,
+        config::config_functions::*,
+        level::level_data::*,
+        replay::replay_data::*,
+        replay::replay_functions::*,
+        events::events_data::*,
+        events::events_functions::*,
+        bindings::bindings_data::*,
+        gamepad::gamepad_data::*,
+        gamepad::gamepad_functions::*,
+        steering::steering_functions::*,
+        pointer::pointer_functions::*,
+        theme::theme_data::*,
+        theme::theme_functions::*,
+        effects::effects_data::*,
+        effects::effects_functions::*,
+        audio::audio_data::*,
+        audio::audio_functions::*,
     };
-    impl Plugin for SnakeGame {
+    /// The rules of the game: resources, events, steering input and the
+    /// systems that move the snake, place fruit and end rounds. It loads no
+    /// assets and only writes files it is given paths for, so it runs under
+    /// `MinimalPlugins` as well as `DefaultPlugins`. Rounds start on
+    /// `EventRestart`.
+    pub struct SnakeCorePlugin {
+        pub config: GameConfig,
+        pub level: Level,
+        pub seed: Option<u64>,
+        pub pilot: Mutex<Option<Box<dyn Controller>>>,
+        /// Where the leaderboards are saved. Kept in memory only when `None`.
+        pub high_scores: Option<PathBuf>,
+    }
+
+    /// Sprites for the board, snake and fruit, the themes they are drawn
+    /// with, effects, and optionally the cameras. Mouse and touch steering
+    /// live here too, as they map the screen onto the board. Add it after
+    /// `SnakeCorePlugin`.
+    pub struct SnakeRenderPlugin {
+        pub cameras: bool,
+        pub walls: bool,
+    }
+
+    /// Score, menus, settings and leaderboards, including the title, pause
+    /// and game over screens and initials entry. Add it after
+    /// `SnakeRenderPlugin`, whose themes it uses. Without it the host app
+    /// starts rounds with `EventRestart` and moves `GameFlow` itself.
+    pub struct SnakeUiPlugin;
+
+    /// Sounds for eating fruit and dying. Needs Bevy's audio plugin, which
+    /// `DefaultPlugins` includes.
+    pub struct SnakeAudioPlugin {
+        pub sounds: SoundPaths,
+    }
+
+    impl Plugin for SnakeCorePlugin {
         fn build(&self, app: &mut AppBuilder) {
-            app.add_resource(GameState { 
-                    // difficulty: 25.0,
+            let config = self.config.clone();
+            let pilot = self.pilot.lock().ok().and_then(|mut pilot| pilot.take());
+            // Headless apps built on `MinimalPlugins` have no input or window.
+            if !app.resources().contains::<Input<KeyCode>>() {
+                app.add_resource(Input::<KeyCode>::default());
+            }
+            if !app.resources().contains::<Input<GamepadButton>>() {
+                app.add_resource(Input::<GamepadButton>::default())
+                    .add_resource(Axis::<GamepadAxis>::default())
+                    .add_event::<GamepadEvent>();
+            }
+            app.add_resource(GameState {
                     score: 0,
-                    playing: true, 
-                    play_area: 600.0,
-                    cell_size: 25.0,
-                    prev_scores: Vec::new()
+                    play_area: config.play_area,
+                    cell_size: config.cell_size,
+                    elapsed: 0.0,
+                    mode: config.mode,
+                    difficulty: config.difficulty,
                 })
-                .add_resource(GameTimer(Timer::from_seconds(0.25, true)))
+                .add_resource(GameTimer(Timer::from_seconds(config.tick_seconds.get(config.difficulty), true)))
+                .add_resource(KeyBindings::from_config(&config.controls))
+                .add_resource(GamepadAssignments::default())
+                .add_resource(config)
+                .add_resource(GameRng::new(self.seed))
+                .add_resource(self.level.clone())
+                .add_resource(ReplayRecorder::default())
+                .add_resource(ReplayPlayer::default())
+                .add_resource(HighScores::load(self.high_scores.clone()))
+                .add_resource(GameFlow::default())
+                .add_resource(RoundStats::default())
+                .add_resource(LastRound::default())
+                .add_resource(Pilot(pilot))
+                .add_resource(DatasetRecorder::default())
                 // .add_resource( Grid {
                 //     cells: Vec::new()
                 // })
-                .add_startup_system(setup.system())
-                .add_startup_system(init_ui.system())
+                .add_startup_system(announce_level.system())
+                .add_stage_after(stage::PRE_UPDATE, STATE_TRANSITION)
+                .add_system_to_stage(STATE_TRANSITION, apply_state_transitions.system())
                 // .add_startup_system(grid_init.system())
                 .add_system(restart.system())
                 .add_system(game_over.system())
                 .add_system(fruit_spawner.system())
+                .add_system(track_gamepads.system())
+                .add_system(gamepad_steering.system())
+                .add_system(relative_steering.system())
                 .add_system(snake_movement.system())
+                .add_system(pilot_steering.system())
+                .add_system(record_dataset.system())
+                .add_system(flush_dataset.system())
+                .add_system(reset_dataset.system())
+                .add_system(reset_replay.system())
+                .add_system(save_replay.system())
                 .add_system(snake_collision.system())
                 .add_system(grow_tail_listener.system())
                 .add_system(move_tail_listener.system())
-                .add_system(update_score.system())
-                .add_system(update_high_scores.system())
+                .add_system(round_clock.system())
+                .add_system(quit_round.system())
                 .add_event::<EventGrowTail>()
                 .add_event::<EventMoveTail>()
                 .add_event::<EventGameOver>()
-                .add_event::<EventRestart>();
+                .add_event::<EventRestart>()
+                .add_event::<EventStateChange>()
+                .add_event::<EventFruitEaten>()
+                .add_event::<EventScoreChanged>()
+                .add_event::<EventSnakeTurned>()
+                .add_event::<EventSnakeDied>()
+                .add_event::<EventRoundStarted>()
+                .add_event::<EventRoundEnded>()
+                .add_event::<EventLevelLoaded>();
         }
     }
-    pub struct SnakeGame;
-
-    fn setup(
-        mut commands: Commands,
-        mut game: ResMut<GameState>,
-        mut materials: ResMut<Assets<ColorMaterial>>,
-    ){
-        let cell_size = game.cell_size as f32;
-        let snake_pos = Vec2::new(0.0, -6.0);
-        let last_pos = Vec2::new(-1.0, -6.0);
-        game.prev_scores.push(0);
-        game.prev_scores.push(0);
-        game.prev_scores.push(0);
-        commands
-            .spawn(Camera2dComponents::default())
-            .spawn(UiCameraComponents::default())
-            .spawn(SpriteComponents {
-                material: materials.add(Color::rgb(0.0, 1.0, 0.0).into()),
-                transform: Transform::from_translation(Vec3::new(0.0, snake_pos.y() * game.cell_size as f32, 0.0)),
-                sprite: Sprite::new(Vec2::new(cell_size - 2.0, cell_size - 2.0)),
-                ..Default::default()
-            })
-            .with(Snake { 
-                direction: SnakeDirection::RIGHT,
-                position: snake_pos,
-                last_position: last_pos,
-                movement_locked: false,
-                next_move: SnakeDirection::RIGHT
-            })
-            .with(Collider::Snake);
-            let wall_material = materials.add(Color::rgb(0.8, 0.8, 0.8).into());
-            let wall_thickness = cell_size;
-            let bounds = Vec2::new(game.play_area, game.play_area);
-        
-        commands
-            // left
-            .spawn(SpriteComponents {
-                material: wall_material.clone(),
-                transform: Transform::from_translation(Vec3::new(-bounds.x() / 2.0, 0.0, 0.0)),
-                sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
-                ..Default::default()
-            })
-            .with(Collider::Solid)
-            // right
-            .spawn(SpriteComponents {
-                material: wall_material.clone(),
-                transform: Transform::from_translation(Vec3::new(bounds.x() / 2.0, 0.0, 0.0)),
-                sprite: Sprite::new(Vec2::new(wall_thickness, bounds.y() + wall_thickness)),
-                ..Default::default()
-            })
-            .with(Collider::Solid)
-            // bottom
-            .spawn(SpriteComponents {
-                material: wall_material.clone(),
-                transform: Transform::from_translation(Vec3::new(0.0, -bounds.y() / 2.0, 0.0)),
-                sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
-                ..Default::default()
-            })
-            .with(Collider::Solid)
-            // top
-            .spawn(SpriteComponents {
-                material: wall_material,
-                transform: Transform::from_translation(Vec3::new(0.0, bounds.y() / 2.0, 0.0)),
-                sprite: Sprite::new(Vec2::new(bounds.x() + wall_thickness, wall_thickness)),
-                ..Default::default()
-            })
-            .with(Collider::Solid);
-        println!("SNAKE!");
+
+    impl Plugin for SnakeRenderPlugin {
+        fn build(&self, app: &mut AppBuilder) {
+            let display = app
+                .resources()
+                .get::<GameConfig>()
+                .map(|config| config.display.clone())
+                .unwrap_or_default();
+            let mut themes = Themes::load(&themes_dir(), &display.theme);
+            themes.palette = display.palette;
+            app.add_resource(themes)
+                .add_resource(ThemeAssets::default())
+                .add_resource(EffectsState::default())
+                .add_startup_system_to_stage(startup_stage::PRE_STARTUP, load_theme.system())
+                .add_startup_system(spawn_obstacles.system())
+                .add_system(pointer_steering.system())
+                .add_system(spawn_bursts.system())
+                .add_system(update_particles.system())
+                .add_system(update_flash.system())
+                .add_system(shake_camera.system())
+                .add_system_to_stage(stage::POST_UPDATE, apply_theme.system())
+                .add_system_to_stage(stage::POST_UPDATE, attach_sprites.system())
+                .add_system_to_stage(stage::POST_UPDATE, trigger_effects.system())
+                .add_system_to_stage(stage::POST_UPDATE, orient_heads.system())
+                .add_system_to_stage(stage::POST_UPDATE, orient_sprites.system())
+                .add_system_to_stage(stage::POST_UPDATE, sync_transforms::<Snake>.system())
+                .add_system_to_stage(stage::POST_UPDATE, sync_transforms::<Tail>.system())
+                .add_system_to_stage(stage::POST_UPDATE, sync_transforms::<Fruit>.system())
+                .add_event::<EventThemeChanged>();
+            if self.cameras {
+                app.add_startup_system(spawn_cameras.system());
+            }
+            if self.walls {
+                app.add_startup_system(spawn_walls.system());
+            }
+        }
+    }
+
+    impl Plugin for SnakeUiPlugin {
+        fn build(&self, app: &mut AppBuilder) {
+            if !app.resources().contains::<Events<WindowFocused>>() {
+                app.add_event::<WindowFocused>();
+            }
+            if !app.resources().contains::<ConfigPath>() {
+                app.add_resource(ConfigPath(default_config_path()));
+            }
+            app.add_resource(SettingsMenu::default())
+                .add_resource(ControlsMenu::default())
+                .add_resource(PauseMenu::default())
+                .add_resource(InitialsEntry::default())
+                .add_startup_system(init_ui.system())
+                .add_system(title_input.system())
+                .add_system(pause_input.system())
+                .add_system(pause_on_focus_lost.system())
+                .add_system(game_over_input.system())
+                .add_system(leaderboard_input.system())
+                .add_system(settings_input.system())
+                .add_system(start_initials.system())
+                .add_system(initials_input.system())
+                .add_system(update_score.system())
+                .add_system(update_high_scores.system())
+                .add_system(update_screen_text.system())
+                .add_system(update_initials_text.system())
+                .add_system(update_leaderboard_screen.system())
+                .add_system(update_text_theme.system());
+        }
+    }
+
+    impl Plugin for SnakeAudioPlugin {
+        fn build(&self, app: &mut AppBuilder) {
+            app.add_resource(self.sounds.clone())
+                .add_resource(Sounds::default())
+                .add_startup_system(load_sounds.system())
+                .add_system(play_sounds.system());
+        }
+    }
+
+    impl Plugin for SnakeGame {
+        fn build(&self, app: &mut AppBuilder) {
+            let pilot = self.pilot.lock().ok().and_then(|mut pilot| pilot.take());
+            app.add_plugin(SnakeCorePlugin {
+                    config: self.config.clone(),
+                    level: self.level.clone(),
+                    seed: self.seed,
+                    pilot: Mutex::new(pilot),
+                    high_scores: self.high_scores.clone(),
+                })
+                .add_plugin(SnakeRenderPlugin {
+                    cameras: self.cameras,
+                    walls: self.walls,
+                });
+            if self.ui {
+                app.add_plugin(SnakeUiPlugin);
+            }
+            if self.audio {
+                app.add_plugin(SnakeAudioPlugin {
+                    sounds: self.sounds.clone(),
+                });
+            }
+        }
+    }
+
+    /// The whole snake game as a Bevy plugin, adding `SnakeCorePlugin`,
+    /// `SnakeRenderPlugin`, `SnakeUiPlugin` and `SnakeAudioPlugin`. `SnakeGame::default()` is the
+    /// standalone game; `SnakeGame::builder()` changes it for embedding in
+    /// another app.
+    pub struct SnakeGame {
+        pub config: GameConfig,
+        pub level: Level,
+        /// Seed for fruit placement, used for every round. A new seed is
+        /// picked each round when `None`.
+        pub seed: Option<u64>,
+        /// Controller steering the snake in place of the keyboard, handed
+        /// over to the app when the plugin is built.
+        pub pilot: Mutex<Option<Box<dyn Controller>>>,
+        /// Spawn a 2D camera and a UI camera.
+        pub cameras: bool,
+        /// Draw the walls around the board.
+        pub walls: bool,
+        /// Show the score, menus and leaderboards.
+        pub ui: bool,
+        /// Play sounds.
+        pub audio: bool,
+        /// Files played for each sound, under the asset root.
+        pub sounds: SoundPaths,
+        /// Where the leaderboards are saved. Off by default, so an app
+        /// embedding the game writes nothing to the user's data directory.
+        pub high_scores: Option<PathBuf>,
+    }
+
+    pub struct SnakeGameBuilder {
+        game: SnakeGame,
+    }
+
+    impl Default for SnakeGame {
+        fn default() -> Self {
+            SnakeGame {
+                config: GameConfig::default(),
+                level: Level::default(),
+                seed: None,
+                pilot: Mutex::new(None),
+                cameras: true,
+                walls: true,
+                ui: true,
+                audio: true,
+                sounds: SoundPaths::default(),
+                high_scores: None,
+            }
+        }
     }
-}
 
+    impl SnakeGame {
+        pub fn builder() -> SnakeGameBuilder {
+            SnakeGameBuilder { game: SnakeGame::default() }
+        }
+    }
+
+    impl SnakeGameBuilder {
+        /// Replaces everything a config file covers at once.
+        pub fn config(mut self, config: GameConfig) -> Self {
+            self.game.config = config;
+            self
+        }
+
+        /// A board `cells` cells across, each `cell_size` pixels wide.
+        pub fn grid_size(mut self, cells: u32, cell_size: f64) -> Self {
+            self.game.config.play_area = cells as f32 * cell_size as f32;
+            self.game.config.cell_size = cell_size;
+            self
+        }
+
+        /// Seconds between moves for each difficulty.
+        pub fn tick_seconds(mut self, tick_seconds: TickSeconds) -> Self {
+            self.game.config.tick_seconds = tick_seconds;
+            self
+        }
+
+        pub fn mode(mut self, mode: GameMode) -> Self {
+            self.game.config.mode = mode;
+            self
+        }
+
+        pub fn difficulty(mut self, difficulty: Difficulty) -> Self {
+            self.game.config.difficulty = difficulty;
+            self
+        }
+
+        /// Name of the theme in `assets/themes` to start with.
+        pub fn theme(mut self, name: &str) -> Self {
+            self.game.config.display.theme = name.to_string();
+            self
+        }
+
+        /// Draws the board, snake, fruit and text in `palette` instead of
+        /// the theme's colors.
+        pub fn colors(mut self, palette: Palette) -> Self {
+            self.game.config.display.palette = Some(palette);
+            self
+        }
+
+        pub fn level(mut self, level: Level) -> Self {
+            self.game.level = level;
+            self
+        }
+
+        pub fn seed(mut self, seed: Option<u64>) -> Self {
+            self.game.seed = seed;
+            self
+        }
+
+        pub fn pilot(mut self, controller: Option<Box<dyn Controller>>) -> Self {
+            self.game.pilot = Mutex::new(controller);
+            self
+        }
+
+        /// Turn off to use the app's own cameras.
+        pub fn cameras(mut self, cameras: bool) -> Self {
+            self.game.cameras = cameras;
+            self
+        }
+
+        pub fn walls(mut self, walls: bool) -> Self {
+            self.game.walls = walls;
+            self
+        }
+
+        pub fn ui(mut self, ui: bool) -> Self {
+            self.game.ui = ui;
+            self
+        }
+
+        /// Turn off to keep the game quiet, or to run without Bevy's audio
+        /// plugin.
+        pub fn audio(mut self, audio: bool) -> Self {
+            self.game.audio = audio;
+            self
+        }
+
+        /// Plays the app's own files, paths under its asset root, in place
+        /// of `sounds/fruit.mp3` and `sounds/death.mp3`.
+        pub fn sounds(mut self, sounds: SoundPaths) -> Self {
+            self.game.sounds = sounds;
+            self
+        }
+
+        /// Saves the leaderboards to `path`, e.g. `high_scores_path()`.
+        pub fn high_scores(mut self, path: Option<PathBuf>) -> Self {
+            self.game.high_scores = path;
+            self
+        }
+
+        /// Checks the settings and returns the plugin.
+        pub fn build(self) -> Result<SnakeGame, String> {
+            let config = &self.game.config;
+            config.validate()?;
+            self.game.level.validate(grid_max(config.play_area, config.cell_size))?;
+            Ok(self.game)
+        }
+    }
+}
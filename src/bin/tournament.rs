@@ -0,0 +1,98 @@
+use std::env;
+use std::fs;
+use std::process;
+
+use snake_game::{
+    controller::controller_data::*,
+    controller::controller_functions::*,
+    tournament::tournament_data::*,
+    tournament::tournament_functions::*,
+};
+
+const USAGE: &str = "\
+Runs headless snake matches between controllers and prints a results table.
+
+USAGE:
+    tournament [OPTIONS] <CONTROLLER> <CONTROLLER>...
+
+CONTROLLERS:
+    greedy                  built-in fruit chaser
+    random[:<seed>]         built-in random mover
+    genome:<file>           genome trained by `trainer`, or a checkpoint's best
+    cmd:<program args...>   external bot speaking the line protocol
+
+OPTIONS:
+    --bracket <round-robin|swiss>   bracket type (default: round-robin)
+    --rounds <n>                    number of Swiss rounds (default: 3)
+    --seeds <a,b,c>                 fixed seeds every pairing is played on (default: 1,2,3)
+    --players <n>                   snakes per match (default: 2)
+    --max-ticks <n>                 tick limit per match (default: 2000)
+    --output <file>                 also write the results as CSV
+    -h, --help                      print this message";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        eprintln!();
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), String> {
+    let mut config = TournamentConfig::default();
+    let mut rounds = 3;
+    let mut swiss = false;
+    let mut output: Option<String> = None;
+    let mut specs: Vec<String> = Vec::new();
+
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--bracket" => match value("--bracket")?.as_str() {
+                "round-robin" => swiss = false,
+                "swiss" => swiss = true,
+                other => return Err(format!("unknown bracket `{}`", other)),
+            },
+            "--rounds" => rounds = parse_number(&value("--rounds")?, "--rounds")?,
+            "--seeds" => {
+                config.seeds = value("--seeds")?
+                    .split(',')
+                    .map(|seed| parse_number(seed.trim(), "--seeds"))
+                    .collect::<Result<_, _>>()?;
+            }
+            "--players" => config.snakes_per_match = parse_number(&value("--players")?, "--players")?,
+            "--max-ticks" => config.max_ticks = parse_number(&value("--max-ticks")?, "--max-ticks")?,
+            "--output" => output = Some(value("--output")?),
+            other if other.starts_with("--") => return Err(format!("unknown option `{}`", other)),
+            spec => specs.push(spec.to_string()),
+        }
+    }
+    if swiss {
+        config.bracket = Bracket::Swiss { rounds };
+    }
+    if specs.len() < 2 {
+        return Err("at least two controllers are needed".to_string());
+    }
+
+    let mut controllers: Vec<Box<dyn Controller>> = specs
+        .iter()
+        .map(|spec| controller_from_spec(spec))
+        .collect::<Result<_, _>>()?;
+    let standings = run_tournament(&mut controllers, &config);
+
+    print!("{}", results_table(&standings));
+    if let Some(path) = output {
+        fs::write(&path, results_csv(&standings)).map_err(|err| format!("could not write `{}`: {}", path, err))?;
+    }
+    Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{}` for {}", value, name))
+}
@@ -0,0 +1,132 @@
+use std::env;
+use std::fs;
+use std::path::PathBuf;
+use std::process;
+
+use snake_game::{
+    training::training_data::*,
+    training::training_functions::*,
+};
+
+const USAGE: &str = "\
+Evolves a heuristic snake controller over many headless games.
+
+USAGE:
+    trainer [OPTIONS]
+
+OPTIONS:
+    --population <n>          genomes per generation (default: 50)
+    --generations <n>         generation to stop at (default: 100)
+    --mutation-rate <p>       chance each weight of a child mutates (default: 0.2)
+    --mutation-strength <x>   largest change a mutation makes (default: 0.5)
+    --elite <n>               best genomes kept unchanged (default: 5)
+    --games <n>               games played per genome (default: 3)
+    --max-ticks <n>           tick limit per game (default: 2000)
+    --fitness <fruit,tick,death>
+                              points per fruit, per tick survived and for dying
+                              (default: 100,0.1,50)
+    --seed <n>                seed for the run (default: 1)
+    --checkpoint-dir <dir>    where checkpoints and best.ron go (default: checkpoints)
+    --checkpoint-every <n>    generations between checkpoints (default: 10)
+    --resume <file>           continue from a saved checkpoint, with the settings
+                              it was started with unless given here
+    -h, --help                print this message
+
+The best genome is written to <checkpoint-dir>/best.ron and can be played with
+`snake_bevy --pilot genome:<file>` or entered in a tournament as `genome:<file>`.";
+
+fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        eprintln!();
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), String> {
+    let args: Vec<String> = env::args().skip(1).collect();
+    // A resumed run keeps the settings it was started with; options given
+    // on the command line are applied on top of them.
+    let start = match args.iter().position(|arg| arg == "--resume") {
+        Some(index) => {
+            let path = args.get(index + 1).ok_or_else(|| "--resume needs a value".to_string())?;
+            Some(load_checkpoint(&PathBuf::from(path))?)
+        }
+        None => None,
+    };
+    let mut config = match &start {
+        Some(checkpoint) => checkpoint.config.clone(),
+        None => TrainingConfig::default(),
+    };
+    let mut checkpoint_dir = PathBuf::from("checkpoints");
+    let mut checkpoint_every = 10;
+
+    let mut args = args.into_iter();
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(());
+            }
+            "--population" => config.population = parse_number(&value("--population")?, "--population")?,
+            "--generations" => config.generations = parse_number(&value("--generations")?, "--generations")?,
+            "--mutation-rate" => config.mutation_rate = parse_number(&value("--mutation-rate")?, "--mutation-rate")?,
+            "--mutation-strength" => {
+                config.mutation_strength = parse_number(&value("--mutation-strength")?, "--mutation-strength")?
+            }
+            "--elite" => config.elite = parse_number(&value("--elite")?, "--elite")?,
+            "--games" => config.games_per_genome = parse_number(&value("--games")?, "--games")?,
+            "--max-ticks" => config.max_ticks = parse_number(&value("--max-ticks")?, "--max-ticks")?,
+            "--fitness" => {
+                let weights: Vec<f64> = value("--fitness")?
+                    .split(',')
+                    .map(|weight| parse_number(weight.trim(), "--fitness"))
+                    .collect::<Result<_, _>>()?;
+                if weights.len() != 3 {
+                    return Err("--fitness takes three values: fruit,tick,death".to_string());
+                }
+                config.fitness = FitnessWeights {
+                    per_fruit: weights[0],
+                    per_tick: weights[1],
+                    death_penalty: weights[2],
+                };
+            }
+            "--seed" => config.seed = parse_number(&value("--seed")?, "--seed")?,
+            "--checkpoint-dir" => checkpoint_dir = PathBuf::from(value("--checkpoint-dir")?),
+            "--checkpoint-every" => checkpoint_every = parse_number(&value("--checkpoint-every")?, "--checkpoint-every")?,
+            "--resume" => {
+                value("--resume")?;
+            }
+            other => return Err(format!("unknown option `{}`", other)),
+        }
+    }
+    config.validate()?;
+
+    fs::create_dir_all(&checkpoint_dir)
+        .map_err(|err| format!("could not create `{}`: {}", checkpoint_dir.display(), err))?;
+
+    let mut save_error = None;
+    let checkpoint = train(&config, start, |checkpoint| {
+        println!("generation {:>4}  best fitness {:>10.2}", checkpoint.generation, checkpoint.best_fitness);
+        if checkpoint_every > 0 && checkpoint.generation % checkpoint_every == 0 {
+            let path = checkpoint_dir.join(format!("generation_{:04}.ron", checkpoint.generation));
+            if let Err(err) = save_checkpoint(checkpoint, &path) {
+                save_error = Some(err);
+            }
+        }
+    })?;
+    if let Some(err) = save_error {
+        return Err(err);
+    }
+
+    save_checkpoint(&checkpoint, &checkpoint_dir.join("latest.ron"))?;
+    save_genome(&checkpoint.best, &checkpoint_dir.join("best.ron"))?;
+    println!("best genome written to {}", checkpoint_dir.join("best.ron").display());
+    Ok(())
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{}` for {}", value, name))
+}
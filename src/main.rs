@@ -1,14 +1,292 @@
-use bevy::{
-    prelude::*,
-    render::pass::ClearColor,
-};
+use bevy::app::AppExit;
+use bevy::prelude::*;
+use rand::Rng;
+use std::env;
+use std::path::PathBuf;
+use std::process;
+use std::sync::Mutex;
 
 use snake_plugin::plugin::*;
+use snake_game::{
+    controller::controller_data::*,
+    controller::controller_functions::*,
+    dataset::dataset_data::*,
+    config::config_data::*,
+    config::config_functions::*,
+    events::events_data::*,
+    game::game_data::*,
+    level::level_data::*,
+    replay::replay_data::*,
+    scores::scores_functions::*,
+    snake::snake_data::*,
+    snake::snake_functions::*,
+    state::state_data::*,
+};
+
+const USAGE: &str = "\
+Plays snake.
+
+USAGE:
+    snake_bevy [OPTIONS]
+
+OPTIONS:
+    --config <file>             config file (default: snake.ron in the user
+                                config directory, if present)
+    --seed <n>                  seed for fruit placement, the same every round
+    --level <file>              level with obstacles to play on
+    --mode <classic|wrap>       game mode, overriding the config
+    --difficulty <easy|normal|hard>
+                                difficulty, overriding the config
+    --record-replay <file>      save each finished round as a replay
+    --replay <file>             play a saved replay back
+    --window-size <WxH>         window size in pixels, e.g. 1280x720
+    --pilot <controller>        let a bot steer, e.g. greedy or genome:<file>
+    --record-dataset <file>     append every human game to a CSV for imitation learning
+    --headless                  play one round with the pilot (default: greedy),
+                                or the --replay, without opening a window and
+                                print the result
+    --max-ticks <n>             tick limit for --headless (default: 10000)
+    -h, --help                  print this message";
+
+#[derive(Default)]
+struct Options {
+    config: Option<PathBuf>,
+    seed: Option<u64>,
+    level: Option<PathBuf>,
+    mode: Option<GameMode>,
+    difficulty: Option<Difficulty>,
+    record_replay: Option<PathBuf>,
+    replay: Option<PathBuf>,
+    window_size: Option<(u32, u32)>,
+    pilot: Option<String>,
+    dataset: Option<PathBuf>,
+    headless: bool,
+    max_ticks: Option<u32>,
+}
 
 fn main() {
+    if let Err(err) = run() {
+        eprintln!("error: {}", err);
+        eprintln!();
+        eprintln!("{}", USAGE);
+        process::exit(1);
+    }
+}
+
+fn run() -> Result<(), String> {
+    let options = match parse_options()? {
+        Some(options) => options,
+        None => return Ok(()),
+    };
+
+    let config_path = options.config.clone().unwrap_or_else(default_config_path);
+    let mut config = match &options.config {
+        Some(path) if !path.exists() => return Err(format!("config `{}` not found", path.display())),
+        Some(path) => GameConfig::load(path)?,
+        None => GameConfig::load(&config_path)?,
+    };
+    if let Some(mode) = options.mode {
+        config.mode = mode;
+    }
+    if let Some(difficulty) = options.difficulty {
+        config.difficulty = difficulty;
+    }
+    let mut seed = options.seed;
+    let mut level = match &options.level {
+        Some(path) => Level::load(path)?,
+        None => Level::default(),
+    };
+    let mut player = ReplayPlayer::default();
+    if let Some(path) = &options.replay {
+        if options.seed.is_some() || options.level.is_some() || options.pilot.is_some() {
+            return Err("--replay cannot be combined with --seed, --level or --pilot".to_string());
+        }
+        let replay = Replay::load(path)?;
+        config.play_area = replay.play_area;
+        config.cell_size = replay.cell_size;
+        config.mode = replay.mode;
+        config.difficulty = replay.difficulty;
+        config.validate().map_err(|err| format!("invalid replay `{}`: {}", path.display(), err))?;
+        seed = Some(replay.seed);
+        level = replay.level.clone();
+        player = ReplayPlayer::new(&replay);
+    }
+    let pilot = match &options.pilot {
+        Some(spec) => Some(controller_from_spec(spec)?),
+        None => None,
+    };
+
+    if options.headless {
+        return run_headless(&options, config, level, seed, pilot, player);
+    }
+    if options.max_ticks.is_some() {
+        return Err("--max-ticks only applies to --headless".to_string());
+    }
+
+    let mut app = App::build();
+    if let Some((width, height)) = options.window_size {
+        app.add_resource(WindowDescriptor {
+            width,
+            height,
+            title: "snake_bevy".to_string(),
+            ..Default::default()
+        });
+    }
+    let game = SnakeGame::builder()
+        .config(config)
+        .level(level)
+        .seed(seed)
+        .pilot(pilot)
+        .high_scores(high_scores_path())
+        .build()?;
+    app.add_plugins(DefaultPlugins)
+        .add_resource(ConfigPath(config_path))
+        .add_plugin(game)
+        .add_resource(ReplayRecorder::new(options.record_replay.clone()))
+        .add_resource(player)
+        .add_resource(DatasetRecorder::new(options.dataset.clone()))
+        .run();
+    Ok(())
+}
+
+/// Seconds between moves under `--headless`: short enough that every
+/// frame is a tick.
+const HEADLESS_TICK_SECONDS: f32 = 1e-6;
+
+/// Resource: what `--headless` prints once its round is over.
+struct HeadlessRun {
+    name: String,
+    seed: u64,
+    max_ticks: u32,
+}
+
+/// Plays a single round with `SnakeCorePlugin` under `MinimalPlugins`,
+/// as fast as it will go, and prints how it went.
+fn run_headless(
+    options: &Options,
+    mut config: GameConfig,
+    level: Level,
+    seed: Option<u64>,
+    pilot: Option<Box<dyn Controller>>,
+    player: ReplayPlayer,
+) -> Result<(), String> {
+    if options.dataset.is_some() || options.window_size.is_some() {
+        return Err("--record-dataset and --window-size cannot be used with --headless".to_string());
+    }
+    level.validate(grid_max(config.play_area, config.cell_size))?;
+    let pilot = match pilot {
+        Some(controller) => Some(controller),
+        None if options.replay.is_some() => None,
+        None => Some(Box::new(GreedyController) as Box<dyn Controller>),
+    };
+    let run = HeadlessRun {
+        name: pilot.as_ref().map(|controller| controller.name()).unwrap_or_else(|| "replay".to_string()),
+        seed: seed.unwrap_or_else(|| rand::thread_rng().gen()),
+        max_ticks: options.max_ticks.unwrap_or(10_000),
+    };
+    config.tick_seconds = TickSeconds {
+        easy: HEADLESS_TICK_SECONDS,
+        normal: HEADLESS_TICK_SECONDS,
+        hard: HEADLESS_TICK_SECONDS,
+    };
     App::build()
-        .add_plugins(DefaultPlugins)
-        .add_resource(ClearColor(Color::BLACK))
-        .add_plugin(SnakeGame)
+        .add_plugins(MinimalPlugins)
+        .add_plugin(SnakeCorePlugin {
+            config,
+            level,
+            seed: Some(run.seed),
+            pilot: Mutex::new(pilot),
+            high_scores: None,
+        })
+        .add_resource(ReplayRecorder::new(options.record_replay.clone()))
+        .add_resource(player)
+        .add_resource(run)
+        .add_startup_system(start_headless_round.system())
+        .add_system(finish_headless_round.system())
         .run();
-}
\ No newline at end of file
+    Ok(())
+}
+
+fn start_headless_round(mut restart: ResMut<Events<EventRestart>>) {
+    restart.send(EventRestart {});
+}
+
+/// Prints the result and quits once the snake dies or `max_ticks` moves
+/// have been made.
+fn finish_headless_round(
+    run: Res<HeadlessRun>,
+    mut round_reader: Local<EventReader<EventRoundEnded>>,
+    round_events: Res<Events<EventRoundEnded>>,
+    game: Res<GameState>,
+    stats: Res<RoundStats>,
+    flow: Res<GameFlow>,
+    tail_query: Query<&Tail>,
+    mut exit: ResMut<Events<AppExit>>,
+) {
+    let result = match round_reader.iter(&round_events).last() {
+        Some(round) => Some((round.score, round.length, "dead")),
+        None if flow.is(AppState::Playing) && run.max_ticks > 0 && stats.ticks >= run.max_ticks => {
+            Some((game.score, tail_query.iter().len() + 1, "alive"))
+        }
+        None => None,
+    };
+    if let Some((score, length, outcome)) = result {
+        println!(
+            "{} seed {} score {} length {} ticks {} {}",
+            run.name, run.seed, score, length, stats.ticks, outcome
+        );
+        exit.send(AppExit);
+    }
+}
+
+/// Reads the command line, or returns `None` if only help was asked for.
+fn parse_options() -> Result<Option<Options>, String> {
+    let mut options = Options::default();
+    let mut args = env::args().skip(1);
+    while let Some(arg) = args.next() {
+        let mut value = |name: &str| args.next().ok_or_else(|| format!("{} needs a value", name));
+        match arg.as_str() {
+            "-h" | "--help" => {
+                println!("{}", USAGE);
+                return Ok(None);
+            }
+            "--config" => options.config = Some(PathBuf::from(value("--config")?)),
+            "--seed" => options.seed = Some(parse_number(&value("--seed")?, "--seed")?),
+            "--level" => options.level = Some(PathBuf::from(value("--level")?)),
+            "--mode" => {
+                options.mode = Some(match value("--mode")?.as_str() {
+                    "classic" => GameMode::Classic,
+                    "wrap" => GameMode::Wrap,
+                    other => return Err(format!("unknown mode `{}`", other)),
+                })
+            }
+            "--difficulty" => {
+                options.difficulty = Some(match value("--difficulty")?.as_str() {
+                    "easy" => Difficulty::Easy,
+                    "normal" => Difficulty::Normal,
+                    "hard" => Difficulty::Hard,
+                    other => return Err(format!("unknown difficulty `{}`", other)),
+                })
+            }
+            "--record-replay" => options.record_replay = Some(PathBuf::from(value("--record-replay")?)),
+            "--replay" => options.replay = Some(PathBuf::from(value("--replay")?)),
+            "--window-size" => {
+                let size = value("--window-size")?;
+                let mut parts = size.splitn(2, 'x');
+                let width = parse_number(parts.next().unwrap_or(""), "--window-size")?;
+                let height = parse_number(parts.next().unwrap_or(""), "--window-size")?;
+                options.window_size = Some((width, height));
+            }
+            "--pilot" => options.pilot = Some(value("--pilot")?),
+            "--record-dataset" => options.dataset = Some(PathBuf::from(value("--record-dataset")?)),
+            "--headless" => options.headless = true,
+            "--max-ticks" => options.max_ticks = Some(parse_number(&value("--max-ticks")?, "--max-ticks")?),
+            other => return Err(format!("unknown option `{}`", other)),
+        }
+    }
+    Ok(Some(options))
+}
+
+fn parse_number<T: std::str::FromStr>(value: &str, name: &str) -> Result<T, String> {
+    value.parse().map_err(|_| format!("invalid value `{}` for {}", value, name))
+}